@@ -3,10 +3,53 @@ use super::{PBufRd, PBufWr};
 #[cfg(all(not(feature = "std"), feature = "alloc"))]
 extern crate alloc;
 #[cfg(all(not(feature = "std"), feature = "alloc"))]
-use {alloc::vec, alloc::vec::Vec};
+use {alloc::boxed::Box, alloc::vec, alloc::vec::Vec};
+
+// Callback invoked before a variable-capacity buffer grows, with the
+// old and new capacities.  Returns `false` to veto the growth.
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub(crate) type GrowthHook = Box<dyn FnMut(usize, usize) -> bool + Send + Sync>;
 
 #[cfg(feature = "std")]
-use std::io::{ErrorKind, Read, Write};
+use std::io::{BufRead, ErrorKind, Read, Write};
+
+// Settings and state for the less commonly used features of a
+// buffer.  These are kept out of line, and only created when one of
+// those features is first used, so that buffers which don't use them
+// pay nothing in size, and the consume path only has to check
+// whether this exists.
+#[derive(Default)]
+pub(crate) struct PBufExt {
+    pub(crate) debug_preview: usize,
+    pub(crate) history: usize,
+    pub(crate) retained: usize,
+    pub(crate) wipe: bool,
+    #[cfg(feature = "waker")]
+    pub(crate) rd_waker: Option<core::task::Waker>,
+    #[cfg(feature = "waker")]
+    pub(crate) wr_waker: Option<core::task::Waker>,
+    #[cfg(feature = "waker")]
+    pub(crate) in_txn: bool,
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    pub(crate) growth_hook: Option<GrowthHook>,
+}
+
+impl PBufExt {
+    // Clear the state that doesn't survive a reset
+    fn reset(&mut self) {
+        self.retained = 0;
+        #[cfg(feature = "waker")]
+        {
+            self.rd_waker = None;
+            self.wr_waker = None;
+        }
+    }
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub(crate) type ExtBox = Box<PBufExt>;
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+pub(crate) type ExtBox = PBufExt;
 
 /// Efficient byte-pipe buffer
 ///
@@ -26,21 +69,34 @@ pub struct PipeBuf<T: 'static = u8> {
     pub(crate) state: PBufState,
     #[cfg(any(feature = "alloc", feature = "std"))]
     pub(crate) fixed_capacity: bool,
+    pub(crate) paused: bool,
+    pub(crate) relaxed: bool,
+    pub(crate) overflowed: bool,
+    #[cfg(feature = "poison")]
+    pub(crate) poison: Option<T>,
+    pub(crate) ext: Option<ExtBox>,
 }
 
 impl<T: Copy + Default + 'static> PipeBuf<T> {
-    /// Create a new empty pipe buffer
+    /// Create a new empty pipe buffer.  This is a `const` function,
+    /// so it may be used to initialise a `static` or `const`.
     #[cfg(any(feature = "std", feature = "alloc"))]
     #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
     #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
     #[inline]
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             data: Vec::new(),
             rd: 0,
             wr: 0,
             state: PBufState::Open,
             fixed_capacity: false,
+            paused: false,
+            relaxed: false,
+            overflowed: false,
+            #[cfg(feature = "poison")]
+            poison: None,
+            ext: None,
         }
     }
 
@@ -56,6 +112,12 @@ impl<T: Copy + Default + 'static> PipeBuf<T> {
             wr: 0,
             state: PBufState::Open,
             fixed_capacity: false,
+            paused: false,
+            relaxed: false,
+            overflowed: false,
+            #[cfg(feature = "poison")]
+            poison: None,
+            ext: None,
         }
     }
 
@@ -74,14 +136,51 @@ impl<T: Copy + Default + 'static> PipeBuf<T> {
             wr: 0,
             state: PBufState::Open,
             fixed_capacity: true,
+            paused: false,
+            relaxed: false,
+            overflowed: false,
+            #[cfg(feature = "poison")]
+            poison: None,
+            ext: None,
         }
     }
 
+    /// Create a new variable-capacity pipe buffer containing the
+    /// given data, with a pending normal EOF (state `Closing`).  This
+    /// is intended for testing consumer components' end-of-stream
+    /// handling without having to script a producer.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    #[inline]
+    pub fn closed_with(data: &[T]) -> Self {
+        let mut pb = Self::new();
+        pb.wr().append(data);
+        pb.wr().close();
+        pb
+    }
+
+    /// Create a new variable-capacity pipe buffer containing the
+    /// given data, with a pending abort (state `Aborting`).  This is
+    /// intended for testing consumer components' abort handling
+    /// without having to script a producer.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    #[inline]
+    pub fn aborted_with(data: &[T]) -> Self {
+        let mut pb = Self::new();
+        pb.wr().append(data);
+        pb.wr().abort();
+        pb
+    }
+
     /// Create a new pipe buffer backed by the given static memory.
     /// This is useful for `no_std` without an allocator.  This is a
     /// safe call, but requires use of `unsafe` in caller code because
     /// the caller must guarantee that no other code is using this
-    /// static memory.
+    /// static memory.  This is a `const` function, so it may be used
+    /// to initialise a `static` or `const`.
     ///
     /// ```
     ///# use pipebuf::PipeBuf;
@@ -92,25 +191,52 @@ impl<T: Copy + Default + 'static> PipeBuf<T> {
     #[cfg(feature = "static")]
     #[cfg_attr(docsrs, doc(cfg(feature = "static")))]
     #[inline]
-    pub fn new_static(buffer: &'static mut [T]) -> Self {
+    pub const fn new_static(buffer: &'static mut [T]) -> Self {
         Self {
             data: buffer,
             rd: 0,
             wr: 0,
             state: PBufState::Open,
+            paused: false,
+            relaxed: false,
+            overflowed: false,
+            #[cfg(feature = "poison")]
+            poison: None,
+            ext: None,
         }
     }
 
+    /// Select how many leading elements of unread data are included
+    /// in the `Debug` output of this buffer, which may be useful to
+    /// get a peek at the data in logs.  Byte data is shown escaped as
+    /// a byte-string.  By default this is 0, meaning that no data is
+    /// shown, to avoid exposing sensitive data in logs.
+    #[inline]
+    pub fn debug_preview(mut self, len: usize) -> Self {
+        self.ext_mut().debug_preview = len;
+        self
+    }
+
     /// Reset the buffer to its initial state, i.e. in the `Open`
     /// state and empty.  The buffer backing memory is not zeroed, so
     /// malicious code may observe old data in the slice returned by
     /// [`PBufWr::space`].  If sensitive data would be exposed in this
-    /// case, use [`PipeBuf::reset_and_zero`] instead.
+    /// case, use [`PipeBuf::reset_and_zero`] instead.  However if
+    /// wiping is enabled with [`PipeBuf::set_wipe_on_consume`], any
+    /// data still held is zeroed.
     #[inline]
     pub fn reset(&mut self) {
+        if self.wiping() {
+            let v = self.wipe_value();
+            self.data[..self.wr].fill(v);
+        }
         self.rd = 0;
         self.wr = 0;
         self.state = PBufState::Open;
+        self.overflowed = false;
+        if let Some(ref mut ext) = self.ext {
+            ext.reset();
+        }
     }
 
     /// Zero the buffer, and reset it to its initial state.  If a
@@ -123,6 +249,476 @@ impl<T: Copy + Default + 'static> PipeBuf<T> {
         self.rd = 0;
         self.wr = 0;
         self.state = PBufState::Open;
+        self.overflowed = false;
+        if let Some(ref mut ext) = self.ext {
+            ext.reset();
+        }
+    }
+
+    /// Move the unread data into a new variable-capacity pipe buffer,
+    /// along with the current EOF/push state.  This buffer is left
+    /// empty and in the `Closed` state (or `Aborted` if it had been
+    /// aborted), since the new buffer now takes over the rest of the
+    /// stream.  This is useful for protocol upgrades (e.g. HTTP to
+    /// WebSocket) where any data following the upgrade must be passed
+    /// to a different chain of components.  If wiping is enabled with
+    /// [`PipeBuf::set_wipe_on_consume`], then the new buffer wipes
+    /// too, and this buffer's copy of the data is zeroed.
+    ///
+    /// Note that since the data is removed and the state moves on at
+    /// the same time, a tripwire comparison across this call may
+    /// miss the change.  For example an `Open` buffer holding two
+    /// elements gives the same tripwire value as the empty `Closed`
+    /// buffer that is left behind.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn split_off(&mut self) -> Self {
+        let mut pb = Self::new();
+        pb.set_wipe_on_consume(matches!(self.ext, Some(ref ext) if ext.wipe));
+        pb.wr().append(&self.data[self.rd..self.wr]);
+        pb.state = self.state;
+        if self.wiping() {
+            let v = self.wipe_value();
+            self.data[..self.wr].fill(v);
+        }
+        self.rd = 0;
+        self.wr = 0;
+        if let Some(ref mut ext) = self.ext {
+            ext.retained = 0;
+        }
+        self.state = match self.state {
+            PBufState::Aborting | PBufState::Aborted => PBufState::Aborted,
+            _ => PBufState::Closed,
+        };
+        pb
+    }
+
+    /// Compact the buffer, by moving any unread data down to the
+    /// start of the backing memory.  Normally this happens
+    /// automatically when a [`PBufWr::space`] call needs more room,
+    /// but latency-sensitive glue code may prefer to choose when the
+    /// copy happens, for example during idle periods rather than in
+    /// the middle of a burst of data.
+    ///
+    /// If a history window has been configured with
+    /// [`PipeBuf::set_history`], then the retained consumed data is
+    /// moved down along with the unread data.
+    #[inline]
+    pub fn compact(&mut self) {
+        let start = self.rd - self.retained();
+        if start > 0 {
+            self.data.copy_within(start..self.wr, 0);
+            if self.wiping() {
+                let v = self.wipe_value();
+                self.data[self.wr - start..self.wr].fill(v);
+            }
+            self.wr -= start;
+            self.rd -= start;
+        }
+    }
+
+    /// Configure a history window, so that the last `len` bytes
+    /// consumed are kept in the buffer and remain accessible through
+    /// [`PBufRd::history`], rather than being discarded when the
+    /// buffer is compacted.  This is useful for consumers which need
+    /// a bounded look-behind, for example decompressors with
+    /// back-references.  The history is best-effort: when the
+    /// producer needs the space, the oldest retained data is dropped
+    /// before a fixed-capacity buffer is reported as full or a
+    /// variable-capacity buffer is grown.  So for a fixed-capacity
+    /// buffer, the space needed for history should be allowed for
+    /// when sizing it.  By default no history is kept.  If the window is
+    /// enlarged, the extra room is only filled by data consumed after
+    /// the change.
+    #[inline]
+    pub fn set_history(&mut self, len: usize) {
+        if len == 0 && self.ext.is_none() {
+            return;
+        }
+        let rd = self.rd;
+        let old_start = rd - self.retained();
+        let ext = self.ext_mut();
+        ext.history = len;
+        ext.retained = ext.retained.min(len);
+        let start = rd - ext.retained;
+        if self.wiping() {
+            self.wipe_range(old_start, start);
+        }
+    }
+
+    /// Select whether data is zeroed as soon as it has been consumed,
+    /// and whenever it is moved within or out of the buffer's memory,
+    /// so that no stray copies of it are left behind.  This is
+    /// intended for buffers that carry sensitive data such as
+    /// plain-text or key material.  Data retained for
+    /// [`PBufRd::history`] is zeroed when it falls out of the history
+    /// window.  Note that this has a cost on every consume operation.
+    ///
+    /// The buffer is not zeroed when it is dropped, so any data still
+    /// held at that point remains in the released memory.  Call
+    /// [`PipeBuf::reset`] (which zeroes held data when wiping is
+    /// enabled) or [`PipeBuf::reset_and_zero`] before dropping a
+    /// buffer that may still contain sensitive data, for example when
+    /// a session is torn down.
+    #[inline]
+    pub fn set_wipe_on_consume(&mut self, wipe: bool) {
+        if wipe || self.ext.is_some() {
+            self.ext_mut().wipe = wipe;
+        }
+    }
+
+    /// Select a poison value for debugging, or `None` to disable
+    /// poisoning (the default).  When enabled, the free space
+    /// returned by [`PBufWr::space`] and [`PBufWr::try_space`] is
+    /// filled with the poison value, and so are regions reclaimed by
+    /// consuming, compaction, discarding or rollback, in the same
+    /// places as for [`PipeBuf::set_wipe_on_consume`].  So a
+    /// component that reads from reserved space before writing to it,
+    /// or that holds on to stale data across a compaction, will see a
+    /// recognizable pattern such as `0xA5` and will hopefully fail
+    /// loudly in tests.  This has a cost on every operation, so it is
+    /// intended for tests and debug builds only, and requires the
+    /// `poison` feature.
+    #[cfg(feature = "poison")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "poison")))]
+    #[inline]
+    pub fn set_poison(&mut self, poison: Option<T>) {
+        self.poison = poison;
+    }
+
+    /// Select whether the producer may write to the buffer.  When set
+    /// to `false`, the producer sees no free space:
+    /// [`PBufWr::free_space`] returns `Some(0)` and
+    /// [`PBufWr::try_space`] returns `None` for any non-zero
+    /// reservation, regardless of how much space is actually
+    /// available.  This allows the glue code to impose backpressure
+    /// for reasons of policy (rate limits, fairness) independent of
+    /// buffer occupancy.  Like all backpressure in the `PipeBuf`
+    /// model this relies on the producer's cooperation:
+    /// [`PBufWr::space`] and [`PBufWr::append`] still succeed.  The
+    /// setting is not changed by [`PipeBuf::reset`].
+    #[inline]
+    pub fn set_writable(&mut self, writable: bool) {
+        self.paused = !writable;
+        if writable {
+            self.wake_wr();
+        }
+    }
+
+    /// Test whether the producer is currently allowed to write, as
+    /// selected by [`PipeBuf::set_writable`]
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        !self.paused
+    }
+
+    /// Select relaxed overflow handling.  Normally a component that
+    /// commits more data than it reserved, commits data after EOF,
+    /// or consumes, discards or rewinds more data than is available
+    /// causes a panic, since this indicates a bug.  In relaxed mode
+    /// these operations are instead clamped to what is possible (or
+    /// ignored, for a commit after EOF), and a sticky overflow flag
+    /// is set, which the glue code can check with
+    /// [`PipeBuf::has_overflowed`].  This is intended for
+    /// environments such as audio callbacks where a panic is worse
+    /// than losing data.  Note that [`PBufWr::space`] still panics
+    /// if a fixed-capacity buffer is full; use [`PBufWr::try_space`]
+    /// to avoid that.
+    #[inline]
+    pub fn set_relaxed(&mut self, relaxed: bool) {
+        self.relaxed = relaxed;
+    }
+
+    /// Test whether an overflow has occurred in relaxed mode since
+    /// the flag was last cleared.  See [`PipeBuf::set_relaxed`].
+    #[inline]
+    pub fn has_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Clear the sticky overflow flag.  It is also cleared by
+    /// [`PipeBuf::reset`] and [`PipeBuf::reset_and_zero`].
+    #[inline]
+    pub fn clear_overflow(&mut self) {
+        self.overflowed = false;
+    }
+
+    // Called when an operation would overflow.  In relaxed mode
+    // records the overflow and returns `true` to indicate that the
+    // caller should clamp the operation.  Otherwise returns `false`
+    // and the caller should panic.
+    #[inline]
+    pub(crate) fn relaxed_overflow(&mut self) -> bool {
+        self.overflowed |= self.relaxed;
+        self.relaxed
+    }
+
+    // Update the read position after data has been consumed.  The
+    // common case is just a store.
+    #[inline(always)]
+    pub(crate) fn set_rd(&mut self, rd: usize) {
+        if self.is_plain() {
+            self.rd = rd;
+        } else {
+            self.set_rd_ext(rd);
+        }
+    }
+
+    // Update the read position when history, wiping, poisoning or
+    // wakers may be in use: keep the history window, zero the
+    // consumed data that is no longer retained if wiping is enabled,
+    // and wake the producer
+    #[inline(never)]
+    fn set_rd_ext(&mut self, rd: usize) {
+        let old_start = self.rd - self.retained();
+        if let Some(ref mut ext) = self.ext {
+            ext.retained = (ext.retained + (rd - self.rd)).min(ext.history);
+        }
+        if self.wiping() {
+            self.wipe_range(old_start, rd - self.retained());
+        }
+        if rd > self.rd {
+            self.rd = rd;
+            self.wake_wr();
+        }
+    }
+
+    // Test whether none of the optional features that need extra
+    // work on consume are in use
+    #[inline(always)]
+    fn is_plain(&self) -> bool {
+        #[cfg(feature = "poison")]
+        if self.poison.is_some() {
+            return false;
+        }
+        self.ext.is_none()
+    }
+
+    // Get the optional settings, creating them if necessary
+    #[inline(never)]
+    #[cold]
+    pub(crate) fn ext_mut(&mut self) -> &mut PBufExt {
+        self.ext.get_or_insert_with(Default::default)
+    }
+
+    // Get the amount of consumed data currently held as history
+    #[inline(always)]
+    pub(crate) fn retained(&self) -> usize {
+        match self.ext {
+            Some(ref ext) => ext.retained,
+            None => 0,
+        }
+    }
+
+    // Test whether a history window is configured
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub(crate) fn keeps_history(&self) -> bool {
+        matches!(self.ext, Some(ref ext) if ext.history > 0)
+    }
+
+    // Test whether a growth hook is set
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[inline]
+    pub(crate) fn has_growth_hook(&self) -> bool {
+        matches!(self.ext, Some(ref ext) if ext.growth_hook.is_some())
+    }
+
+    /// Register a waker to be woken when the producer next makes a
+    /// change that the consumer may be waiting for: committing data,
+    /// setting "push", or indicating EOF.  This allows async glue
+    /// code to wake the task running the consumer component, instead
+    /// of polling every component on every wakeup.  The waker is
+    /// woken once and then discarded, so it must be registered again
+    /// each time the consumer is about to wait.  Registering replaces
+    /// any previously-registered consumer waker.
+    #[cfg(feature = "waker")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "waker")))]
+    #[inline]
+    pub fn register_rd_waker(&mut self, waker: &core::task::Waker) {
+        let ext = self.ext_mut();
+        match ext.rd_waker {
+            Some(ref w) if w.will_wake(waker) => (),
+            _ => ext.rd_waker = Some(waker.clone()),
+        }
+    }
+
+    /// Register a waker to be woken when the consumer next frees
+    /// space by consuming or discarding data, or when writing is
+    /// re-enabled with [`PipeBuf::set_writable`].  This allows async
+    /// glue code to wake the task running a producer that is waiting
+    /// for space in a fixed-capacity buffer.  As for
+    /// [`PipeBuf::register_rd_waker`], the waker is woken once and
+    /// then discarded.  Registered wakers are dropped by
+    /// [`PipeBuf::reset`].
+    #[cfg(feature = "waker")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "waker")))]
+    #[inline]
+    pub fn register_wr_waker(&mut self, waker: &core::task::Waker) {
+        let ext = self.ext_mut();
+        match ext.wr_waker {
+            Some(ref w) if w.will_wake(waker) => (),
+            _ => ext.wr_waker = Some(waker.clone()),
+        }
+    }
+
+    // Wake the consumer's waker, if any.  Does nothing without the
+    // "waker" feature.
+    #[inline(always)]
+    pub(crate) fn wake_rd(&mut self) {
+        #[cfg(feature = "waker")]
+        if let Some(waker) = self
+            .ext
+            .as_mut()
+            .filter(|ext| !ext.in_txn)
+            .and_then(|ext| ext.rd_waker.take())
+        {
+            waker.wake();
+        }
+    }
+
+    // Mark whether a write transaction is open, returning the
+    // previous setting.  While one is open, consumer wakes are held
+    // back until the outermost transaction commits.  Without an
+    // extension there is no consumer waker, and none can be
+    // registered while the transaction holds the buffer.
+    #[cfg(feature = "waker")]
+    #[inline]
+    pub(crate) fn set_in_txn(&mut self, in_txn: bool) -> bool {
+        match self.ext {
+            Some(ref mut ext) => core::mem::replace(&mut ext.in_txn, in_txn),
+            None => false,
+        }
+    }
+
+    // Wake the producer's waker, if any.  Does nothing without the
+    // "waker" feature.
+    #[inline(always)]
+    pub(crate) fn wake_wr(&mut self) {
+        #[cfg(feature = "waker")]
+        if let Some(waker) = self.ext.as_mut().and_then(|ext| ext.wr_waker.take()) {
+            waker.wake();
+        }
+    }
+
+    // Give up the oldest `len` elements of retained history, to make
+    // room for the producer
+    pub(crate) fn drop_history(&mut self, len: usize) {
+        let Some(ref mut ext) = self.ext else {
+            return;
+        };
+        let len = len.min(ext.retained);
+        let start = self.rd - ext.retained;
+        ext.retained -= len;
+        if self.wiping() {
+            self.wipe_range(start, start + len);
+        }
+    }
+
+    #[inline(never)]
+    pub(crate) fn wipe_range(&mut self, start: usize, end: usize) {
+        if end > start {
+            let v = self.wipe_value();
+            self.data[start..end].fill(v);
+        }
+    }
+
+    // Test whether reclaimed regions need filling, either for wiping
+    // or for poisoning
+    #[inline(always)]
+    pub(crate) fn wiping(&self) -> bool {
+        #[cfg(feature = "poison")]
+        if self.poison.is_some() {
+            return true;
+        }
+        matches!(self.ext, Some(ref ext) if ext.wipe)
+    }
+
+    #[inline(always)]
+    fn wipe_value(&self) -> T {
+        #[cfg(feature = "poison")]
+        return self.poison.unwrap_or_default();
+
+        #[cfg(not(feature = "poison"))]
+        return T::default();
+    }
+
+    // Replace the backing memory with a new allocation of the given
+    // size, copying over the data up to `wr` and zeroing the old
+    // memory before it is released.  Used when wiping is enabled,
+    // since a reallocation within `Vec` would release the old memory
+    // without zeroing it.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    pub(crate) fn realloc_wiped(&mut self, cap: usize) {
+        let mut data = vec![T::default(); cap];
+        let len = self.wr.min(cap);
+        data[..len].copy_from_slice(&self.data[..len]);
+        self.data.fill(T::default());
+        self.data = data;
+    }
+
+    /// Get the current capacity of the buffer's backing memory
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Shrink the allocation of a variable-capacity buffer towards
+    /// `min_cap`, after compacting it.  The capacity never drops below
+    /// the amount of unread data held.  Fixed-capacity buffers are
+    /// unaffected.  Long-lived but mostly-idle buffers may otherwise
+    /// hold on to the allocation they needed during a burst of data.
+    /// Glue code can call this when it sees that a buffer has stayed
+    /// below some threshold for a while, according to its own policy.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn shrink_to(&mut self, min_cap: usize) {
+        if self.fixed_capacity {
+            return;
+        }
+        self.compact();
+        let cap = min_cap.max(self.wr);
+        if cap < self.data.len() {
+            if self.wiping() {
+                self.realloc_wiped(cap);
+            } else {
+                self.data.truncate(cap);
+                self.data.shrink_to_fit();
+            }
+        }
+    }
+
+    /// Set a hook which is called just before a variable-capacity
+    /// buffer grows its allocation, with the old and new capacities
+    /// as arguments.  This allows an application to log or count
+    /// reallocations to help tune initial capacities, or to keep
+    /// track of total buffer memory.  If the hook returns `false`
+    /// then the growth is vetoed, in which case the buffer acts as
+    /// if it were fixed-capacity and full: [`PBufWr::try_space`]
+    /// returns `None` and [`PBufWr::space`] panics.  The hook is not
+    /// called for fixed-capacity buffers, and it is not carried over
+    /// by [`PipeBuf::split_off`].
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn set_growth_hook(
+        &mut self,
+        hook: impl FnMut(usize, usize) -> bool + Send + Sync + 'static,
+    ) {
+        self.ext_mut().growth_hook = Some(Box::new(hook));
+    }
+
+    /// Remove any hook set with [`PipeBuf::set_growth_hook`]
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    #[inline]
+    pub fn clear_growth_hook(&mut self) {
+        if let Some(ref mut ext) = self.ext {
+            ext.growth_hook = None;
+        }
     }
 
     /// Get a consumer reference to the buffer
@@ -161,6 +757,33 @@ impl<T: Copy + Default + 'static> PipeBuf<T> {
         self.tripwire() != trip
     }
 
+    /// Get a snapshot of the buffer's data length, free space and
+    /// state, which glue code may use to decide whether a component
+    /// needs to be run.  See [`PBufReady`].
+    #[inline]
+    pub fn readiness(&self) -> PBufReady {
+        PBufReady {
+            len: self.wr - self.rd,
+            free: self.free_space(),
+            state: self.state,
+        }
+    }
+
+    // Free space as seen by the producer.  See `PBufWr::free_space`.
+    #[inline]
+    pub(crate) fn free_space(&self) -> Option<usize> {
+        if self.paused {
+            return Some(0);
+        }
+        let used = self.wr - self.rd;
+
+        #[cfg(any(feature = "std", feature = "alloc"))]
+        return self.fixed_capacity.then_some(self.data.len() - used);
+
+        #[cfg(not(any(feature = "std", feature = "alloc")))]
+        return Some(self.data.len() - used);
+    }
+
     /// Get the current EOF/push state of the buffer
     #[inline(always)]
     pub fn state(&self) -> PBufState {
@@ -189,6 +812,42 @@ impl<T: Copy + Default + 'static> PipeBuf<T> {
         }
     }
 
+    /// Return a fully-drained buffer whose EOF has been consumed
+    /// (state `Closed` or `Aborted`) to the `Open` state, so that the
+    /// same stream can be reused, for example by a keep-alive
+    /// protocol handling a series of requests.  Unlike
+    /// [`PipeBuf::reset`], nothing else about the buffer is changed.
+    /// Since the producer cannot write after EOF, any unread data
+    /// left in the buffer is the tail of the previous stream, possibly
+    /// an aborted one, so it must not become part of the new stream.
+    /// Returns `true` if the buffer was reopened, or `false` if the
+    /// EOF had not yet been consumed, there was no EOF, or unread data
+    /// remains.
+    #[inline]
+    pub fn reopen(&mut self) -> bool {
+        if self.rd == self.wr && matches!(self.state, PBufState::Closed | PBufState::Aborted) {
+            self.state = PBufState::Open;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Override the EOF/push state of the buffer.  Normally the state
+    /// only moves forwards, as driven by the producer and consumer.
+    /// This call is for the glue code to use in exceptional cases,
+    /// for example to return an `Aborting` buffer to `Open` for error
+    /// recovery or connection reuse, or to force `Closed`.  The
+    /// buffer contents are not affected.
+    ///
+    /// Note that moving the state backwards may cause a tripwire
+    /// comparison to miss a change, if it happens between taking two
+    /// tripwire values that are compared.
+    #[inline]
+    pub fn force_state(&mut self, state: PBufState) {
+        self.state = state;
+    }
+
     /// Test whether an EOF has been indicated and consumed, and for
     /// the case of a `Closed` EOF also that the buffer is empty.
     /// This means that processing on this [`PipeBuf`] is complete
@@ -232,6 +891,43 @@ impl Read for PipeBuf<u8> {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl BufRead for PipeBuf<u8> {
+    /// Get the unread data in the pipe-buffer directly, without
+    /// copying.  The following returns are possible:
+    ///
+    /// - `Ok(data)` with non-empty `data`: Some data is available
+    /// - `Ok(data)` with empty `data`: Successful end-of-file was reached
+    /// - `Err(e)` with `e.kind() == ErrorKind::WouldBlock`: No data available right now
+    /// - `Err(e)` with `e.kind() == ErrorKind::ConnectionAborted`: Aborted end-of-file was reached
+    ///
+    /// Unlike [`PipeBuf::read`], end-of-file continues to be reported
+    /// on further calls, since `BufRead` users expect that.
+    ///
+    /// [`PipeBuf::read`]: struct.PipeBuf.html#method.read
+    fn fill_buf(&mut self) -> Result<&[u8], std::io::Error> {
+        if self.rd == self.wr {
+            let mut rd = self.rd();
+            if !rd.is_eof() {
+                return Err(ErrorKind::WouldBlock.into());
+            }
+            rd.consume_eof();
+            if rd.is_aborted() {
+                return Err(ErrorKind::ConnectionAborted.into());
+            }
+        }
+        Ok(&self.data[self.rd..self.wr])
+    }
+
+    /// Consume data previously returned by `fill_buf`.  Panics if
+    /// `amt` is greater than the number of bytes available.
+    #[track_caller]
+    fn consume(&mut self, amt: usize) {
+        self.rd().consume(amt);
+    }
+}
+
 #[cfg(feature = "std")]
 #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 impl Write for PipeBuf<u8> {
@@ -264,6 +960,37 @@ impl<T: Copy + Default + 'static> Default for PipeBuf<T> {
     }
 }
 
+impl<T: Copy + Default + 'static> Extend<T> for PipeBuf<T> {
+    /// Append the items to the buffer.  See the `Extend`
+    /// implementation on [`PBufWr`].
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.wr().extend(iter)
+    }
+}
+
+impl<'a, T: Copy + Default + 'static> Extend<&'a T> for PipeBuf<T> {
+    /// Append copies of the items to the buffer.  See the `Extend`
+    /// implementation on [`PBufWr`].
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.wr().extend(iter)
+    }
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+impl<T: Copy + Default + 'static> FromIterator<T> for PipeBuf<T> {
+    /// Create a variable-capacity buffer containing the items, in
+    /// the `Open` state
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut pb = Self::new();
+        pb.extend(iter);
+        pb
+    }
+}
+
 /// End-of-file and "push" state of the buffer
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum PBufState {
@@ -321,23 +1048,225 @@ pub enum PBufState {
 /// These scenarios are supported:
 ///
 /// - In a consumer, avoiding re-parsing an input buffer when there
-/// have been no changes made by the producer.  Save a `PBufTrip`
-/// value before returning, and when called the next time, compare it
-/// to the current value.
+///   have been no changes made by the producer.  Save a `PBufTrip`
+///   value before returning, and when called the next time, compare it
+///   to the current value.
 ///
 /// - In the glue code, detect whether a component call has caused
-/// changes to a buffer.
+///   changes to a buffer.
 ///
 /// - In consumer code, check whether some sub-part of the consumer
-/// processing has done something.
+///   processing has done something.
 ///
 /// - In producer code, check whether some sub-part of the producer
-/// processing has done something.
+///   processing has done something.
 ///
 /// [`tripwire!`]: macro.tripwire.html
 #[derive(Eq, PartialEq, Copy, Clone)]
 pub struct PBufTrip(usize);
 
+/// Readiness snapshot of a pipe buffer
+///
+/// Obtained using [`PipeBuf::readiness`].  This captures everything
+/// that a glue-code scheduler typically needs to answer the question
+/// "should I run this component?" in a single call, for both the
+/// consumer and producer sides of the buffer.  It is a copy of the
+/// buffer's status at the time it was taken and is not updated
+/// afterwards.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct PBufReady {
+    len: usize,
+    free: Option<usize>,
+    state: PBufState,
+}
+
+impl PBufReady {
+    /// Get the number of elements of unread data
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Test whether there was no unread data
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Test whether there were at least `n` elements of unread data
+    #[inline]
+    pub fn has_data(&self, n: usize) -> bool {
+        self.len >= n
+    }
+
+    /// Get the free space as seen by the producer.  See
+    /// [`PBufWr::free_space`].
+    #[inline]
+    pub fn free_space(&self) -> Option<usize> {
+        self.free
+    }
+
+    /// Test whether the producer could write at least `n` elements
+    /// without running out of space.  Always `true` for a
+    /// variable-capacity buffer, unless writing was paused with
+    /// [`PipeBuf::set_writable`].
+    #[inline]
+    pub fn has_space(&self, n: usize) -> bool {
+        match self.free {
+            Some(free) => free >= n,
+            None => true,
+        }
+    }
+
+    /// Get the EOF/push state
+    #[inline]
+    pub fn state(&self) -> PBufState {
+        self.state
+    }
+
+    /// Test whether a "push" was pending
+    #[inline]
+    pub fn is_push(&self) -> bool {
+        self.state == PBufState::Push
+    }
+
+    /// Test whether there was an EOF waiting to be consumed (state
+    /// `Closing` or `Aborting`)
+    #[inline]
+    pub fn has_pending_eof(&self) -> bool {
+        matches!(self.state, PBufState::Closing | PBufState::Aborting)
+    }
+
+    /// Test whether the producer had indicated EOF, so no more data
+    /// can be written
+    #[inline]
+    pub fn is_eof(&self) -> bool {
+        !matches!(self.state, PBufState::Open | PBufState::Push)
+    }
+
+    /// Test whether processing on the buffer was complete.  See
+    /// [`PipeBuf::is_done`].
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        match self.state {
+            PBufState::Aborted => true,
+            PBufState::Closed => self.len == 0,
+            _ => false,
+        }
+    }
+}
+
+/// Read-only queries common to [`PipeBuf`], [`PBufRd`] and [`PBufWr`]
+///
+/// This allows helper functions, for example for logging or for
+/// scheduling decisions in glue code, to be written once and accept
+/// any of the three types.  Note that component code should normally
+/// use the methods on [`PBufRd`] and [`PBufWr`] directly.  In
+/// particular a producer should not adapt its behaviour to how the
+/// consumer is doing, as discussed for [`PBufWr::exceeds_limit`].
+///
+/// ```
+///# use pipebuf::{PBufRef, PipeBuf};
+/// fn describe(pb: &impl PBufRef) -> String {
+///     format!("{:?} len={}", pb.state(), pb.len())
+/// }
+///# #[cfg(any(feature = "std", feature = "alloc"))]
+///# {
+/// let mut p = PipeBuf::<u8>::new();
+/// p.wr().append(b"abc");
+/// assert_eq!(describe(&p), describe(&p.rd()));
+///# }
+/// ```
+pub trait PBufRef {
+    /// Obtain a tripwire value to detect buffer changes.  See
+    /// [`PBufTrip`].
+    fn tripwire(&self) -> PBufTrip;
+
+    /// Get a snapshot of the buffer's status.  See [`PBufReady`].
+    fn readiness(&self) -> PBufReady;
+
+    /// Test whether there has been a change to the buffer since the
+    /// tripwire value provided was obtained
+    #[inline]
+    fn is_tripped(&self, trip: PBufTrip) -> bool {
+        self.tripwire() != trip
+    }
+
+    /// Get the current EOF/push state of the buffer
+    #[inline]
+    fn state(&self) -> PBufState {
+        self.readiness().state()
+    }
+
+    /// Get the number of elements of unread data
+    #[inline]
+    fn len(&self) -> usize {
+        self.readiness().len()
+    }
+
+    /// Test whether the buffer is empty
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.readiness().is_empty()
+    }
+
+    /// Get the free space as seen by the producer.  See
+    /// [`PBufWr::free_space`].
+    #[inline]
+    fn free_space(&self) -> Option<usize> {
+        self.readiness().free_space()
+    }
+
+    /// Test whether end-of-file has been indicated by the producer
+    #[inline]
+    fn is_eof(&self) -> bool {
+        self.readiness().is_eof()
+    }
+
+    /// Test whether processing on the buffer is complete.  See
+    /// [`PipeBuf::is_done`].
+    #[inline]
+    fn is_done(&self) -> bool {
+        self.readiness().is_done()
+    }
+}
+
+impl<T: Copy + Default + 'static> PBufRef for PipeBuf<T> {
+    #[inline]
+    fn tripwire(&self) -> PBufTrip {
+        PipeBuf::tripwire(self)
+    }
+
+    #[inline]
+    fn readiness(&self) -> PBufReady {
+        PipeBuf::readiness(self)
+    }
+}
+
+impl<T: Copy + Default + 'static> PBufRef for PBufRd<'_, T> {
+    #[inline]
+    fn tripwire(&self) -> PBufTrip {
+        self.pb.tripwire()
+    }
+
+    #[inline]
+    fn readiness(&self) -> PBufReady {
+        self.pb.readiness()
+    }
+}
+
+impl<T: Copy + Default + 'static> PBufRef for PBufWr<'_, T> {
+    #[inline]
+    fn tripwire(&self) -> PBufTrip {
+        self.pb.tripwire()
+    }
+
+    #[inline]
+    fn readiness(&self) -> PBufReady {
+        self.pb.readiness()
+    }
+}
+
 #[cfg(test)]
 mod test {
     // This test is here so that it can directly check inc/dec of
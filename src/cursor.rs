@@ -0,0 +1,88 @@
+use super::PBufRd;
+
+/// Scan position for an incremental parser
+///
+/// A consumer that cannot yet consume anything, for example because
+/// a record is incomplete, can keep one of these alongside its own
+/// parsing state to remember how far it has already scanned into the
+/// unread data.  On the next call it can then resume from that point
+/// using [`PBufCursor::unscanned`], instead of re-scanning the whole
+/// unconsumed prefix.  A tripwire says that something has changed,
+/// and this says where to carry on from.
+///
+/// The position is relative to the start of the unread data, so it
+/// is unaffected by new data arriving or by compaction.  Since only
+/// the consumer consumes data, it is the consumer's job to report
+/// consumption with [`PBufCursor::consumed`].  If the position gets
+/// out of step with the buffer, for example after a
+/// [`PBufRd::rewind`] or a reset, then call [`PBufCursor::reset`].
+///
+/// ```
+///# use pipebuf::{PBufCursor, PipeBuf};
+///# #[cfg(any(feature = "std", feature = "alloc"))]
+///# {
+/// let mut p = PipeBuf::<u8>::new();
+/// let mut cursor = PBufCursor::new();
+/// p.wr().append(b"abc");
+/// let rd = p.rd();
+/// assert_eq!(None, cursor.unscanned(&rd).iter().position(|&b| b == b'\n'));
+/// cursor.advance(rd.len());
+/// drop(rd);
+/// p.wr().append(b"d\ne");
+/// let mut rd = p.rd();
+/// assert_eq!(b"d\ne", cursor.unscanned(&rd));
+/// rd.consume(5);
+/// cursor.consumed(5);
+/// assert_eq!(b"e", cursor.unscanned(&rd));
+///# }
+/// ```
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub struct PBufCursor {
+    pos: usize,
+}
+
+impl PBufCursor {
+    /// Create a new cursor at the start of the unread data
+    #[inline]
+    pub const fn new() -> Self {
+        Self { pos: 0 }
+    }
+
+    /// Get the number of leading elements of the unread data that
+    /// have already been scanned
+    #[inline]
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Record that a further `len` elements have been scanned
+    #[inline]
+    pub fn advance(&mut self, len: usize) {
+        self.pos += len;
+    }
+
+    /// Report that `len` elements have been consumed from the start
+    /// of the buffer, so that the position stays relative to the
+    /// start of the unread data
+    #[inline]
+    pub fn consumed(&mut self, len: usize) {
+        self.pos = self.pos.saturating_sub(len);
+    }
+
+    /// Reset the position to the start of the unread data, so that
+    /// everything is scanned again
+    #[inline]
+    pub fn reset(&mut self) {
+        self.pos = 0;
+    }
+
+    /// Get the part of the unread data in the buffer that has not yet
+    /// been scanned.  If the position is beyond the end of the data,
+    /// for example because the producer discarded some data, then an
+    /// empty slice is returned.
+    #[inline]
+    pub fn unscanned<'b, T: Copy + Default + 'static>(&self, rd: &'b PBufRd<'_, T>) -> &'b [T] {
+        let data = rd.data();
+        &data[self.pos.min(data.len())..]
+    }
+}
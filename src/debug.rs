@@ -0,0 +1,119 @@
+use super::PipeBuf;
+use core::any::{Any, TypeId};
+use core::fmt;
+
+impl<T: fmt::Debug + 'static> fmt::Debug for PipeBuf<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("PipeBuf");
+        s.field("state", &self.state)
+            .field("len", &(self.wr - self.rd))
+            .field("capacity", &self.data.len());
+        let preview = self.ext.as_ref().map_or(0, |ext| ext.debug_preview);
+        if preview > 0 {
+            let data = &self.data[self.rd..self.wr];
+            let len = data.len().min(preview);
+            s.field(
+                "preview",
+                &Preview {
+                    data: &data[..len],
+                    more: len < data.len(),
+                },
+            );
+        }
+        s.finish()
+    }
+}
+
+// Leading part of the unread data for the `Debug` output.  Bytes are
+// shown as an escaped byte-string, other types as a list.
+struct Preview<'a, T> {
+    data: &'a [T],
+    more: bool,
+}
+
+impl<T: fmt::Debug + 'static> fmt::Debug for Preview<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if TypeId::of::<T>() == TypeId::of::<u8>() {
+            write!(f, "b\"")?;
+            for b in self
+                .data
+                .iter()
+                .filter_map(|v| (v as &dyn Any).downcast_ref::<u8>())
+            {
+                write!(f, "{}", core::ascii::escape_default(*b))?;
+            }
+            write!(f, "\"")?;
+        } else {
+            f.debug_list().entries(self.data).finish()?;
+        }
+        if self.more {
+            write!(f, "...")?;
+        }
+        Ok(())
+    }
+}
+
+impl PipeBuf<u8> {
+    /// Get a hexdump of the unread data in the buffer for debugging,
+    /// showing at most `limit` bytes.  The returned value implements
+    /// `Display`, giving lines of 16 bytes, each with an offset, the
+    /// bytes in hex and an ASCII column.  If the data is truncated
+    /// then a final line gives the number of bytes not shown.
+    ///
+    /// ```
+    ///# use pipebuf::PipeBuf;
+    ///# #[cfg(any(feature = "std", feature = "alloc"))]
+    ///# {
+    /// let mut p = PipeBuf::new();
+    /// p.wr().append(b"GET / HTTP/1.1\r\n");
+    /// println!("{}", p.dump(64));
+    ///# }
+    /// ```
+    pub fn dump(&self, limit: usize) -> PBufDump<'_> {
+        PBufDump {
+            data: &self.data[self.rd..self.wr],
+            limit,
+        }
+    }
+}
+
+/// Hexdump of pipe-buffer contents
+///
+/// Obtained using [`PipeBuf::dump`].  Use the `Display`
+/// implementation to format the hexdump.
+pub struct PBufDump<'a> {
+    data: &'a [u8],
+    limit: usize,
+}
+
+impl fmt::Display for PBufDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.data.len().min(self.limit);
+        for (i, line) in self.data[..len].chunks(16).enumerate() {
+            write!(f, "{:04x} ", i * 16)?;
+            for j in 0..16 {
+                if j == 8 {
+                    write!(f, " ")?;
+                }
+                match line.get(j) {
+                    Some(b) => write!(f, " {:02x}", b)?,
+                    None => write!(f, "   ")?,
+                }
+            }
+            write!(f, "  |")?;
+            for &b in line {
+                let ch = if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                };
+                write!(f, "{}", ch)?;
+            }
+            writeln!(f, "|")?;
+        }
+        if len < self.data.len() {
+            writeln!(f, "... {} more bytes", self.data.len() - len)?;
+        }
+        Ok(())
+    }
+}
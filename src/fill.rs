@@ -0,0 +1,111 @@
+use super::{PBufRd, PBufWr};
+
+/// Source of data which writes into a slice
+///
+/// This is a `no_std` analogue of `std::io::Read`, for example for an
+/// embedded driver to implement, which can then be used with
+/// [`PBufWr::fill_from`] to bring data into a pipe-buffer.  Unlike
+/// `Read`, returning `Ok(0)` means that no data is available right
+/// now, not end-of-file.  Since drivers differ in how EOF is
+/// detected, it is left to the glue code to close the buffer when
+/// appropriate.
+pub trait PBufFiller<T = u8> {
+    /// Error type returned by the source
+    type Error;
+
+    /// Write up to `buf.len()` elements into the start of `buf`, and
+    /// return the number written.  Return `Ok(0)` if no data is
+    /// currently available.
+    fn fill(&mut self, buf: &mut [T]) -> Result<usize, Self::Error>;
+}
+
+/// Sink for data which takes it from a slice
+///
+/// This is a `no_std` analogue of `std::io::Write`, for example for
+/// an embedded driver to implement, which can then be used with
+/// [`PBufRd::drain_to`] to send data out of a pipe-buffer.
+pub trait PBufDrainer<T = u8> {
+    /// Error type returned by the sink
+    type Error;
+
+    /// Accept data from the start of `data`, and return the number
+    /// of elements taken.  Return `Ok(0)` if no more data can be
+    /// accepted right now.
+    fn drain(&mut self, data: &[T]) -> Result<usize, Self::Error>;
+
+    /// Flush any data held by the sink.  By default this does
+    /// nothing.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
+    /// Input data from the given [`PBufFiller`] source, up to the
+    /// given length.  This stops early if the source returns `Ok(0)`
+    /// to indicate that no more data is available right now.  Does
+    /// nothing if EOF has already been indicated on this buffer.
+    /// Returns the number of elements read, or the first error
+    /// returned by the source.
+    ///
+    /// Use a tripwire (see [`PBufWr::tripwire`]) if you need to
+    /// determine whether or not new data was read before an error
+    /// occurred.
+    #[track_caller]
+    pub fn fill_from<F: PBufFiller<T>>(
+        &mut self,
+        source: &mut F,
+        len: usize,
+    ) -> Result<usize, F::Error> {
+        let mut total = 0;
+        if self.is_eof() {
+            return Ok(0);
+        }
+        while total < len {
+            match self.write_with(len - total, |buf| source.fill(buf))? {
+                0 => break,
+                count => total += count,
+            }
+        }
+        Ok(total)
+    }
+}
+
+impl<'a, T: Copy + Default + 'static> PBufRd<'a, T> {
+    /// Output as much data as possible to the given [`PBufDrainer`]
+    /// sink.  The "push" state is converted into a `flush` call if
+    /// the pipe buffer is emptied.  Also a flush can be forced if
+    /// `force_flush` is set to `true`.  End-of-file is not handled
+    /// here, and must be passed on to the sink by the glue code.
+    /// Returns the number of elements output, or the first error
+    /// returned by the sink.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sink claims to have taken more data than it was
+    /// given.
+    #[track_caller]
+    pub fn drain_to<D: PBufDrainer<T>>(
+        &mut self,
+        sink: &mut D,
+        force_flush: bool,
+    ) -> Result<usize, D::Error> {
+        let mut total = 0;
+        while !self.is_empty() {
+            match sink.drain(self.data())? {
+                0 => break,
+                len => {
+                    if len > self.len() {
+                        panic!("Faulty PBufDrainer implementation consumed more data than it was given");
+                    }
+                    self.consume(len);
+                    total += len;
+                }
+            }
+        }
+        if (self.is_empty() && self.consume_push()) || force_flush {
+            sink.flush()?;
+        }
+        Ok(total)
+    }
+}
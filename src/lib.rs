@@ -348,7 +348,11 @@
 //!
 //! If you wish to reuse [`PipeBuf`] instances (e.g. in a buffer
 //! pool), use [`PipeBuf::reset_and_zero`] or [`PipeBuf::reset`] to
-//! prepare the buffer before re-use.
+//! prepare the buffer before re-use.  With `alloc` a ready-made pool
+//! is provided by
+#![cfg_attr(any(feature = "std", feature = "alloc"), doc = "[`PipeBufPool`]")]
+#![cfg_attr(not(any(feature = "std", feature = "alloc")), doc = "`PipeBufPool`")]
+//! .
 //!
 //! If a fixed capacity buffer's capacity is exceeded then the code
 //! will panic rather than make any attempt to reallocate the buffer.
@@ -370,6 +374,29 @@
 //! unless it really needs them, as that would limit the options for
 //! the crate user.
 //!
+//! # Async glue code
+//!
+//! With the `waker` feature, a `core::task::Waker` may be registered
+//! on a [`PipeBuf`] for the consumer side, to be woken when the
+//! producer commits data or changes the state, and for the producer
+//! side, to be woken when the consumer frees space.  This allows
+//! async glue code to wake just the tasks that can make progress.
+//! See
+#![cfg_attr(feature = "waker", doc = "[`PipeBuf::register_rd_waker`]")]
+#![cfg_attr(not(feature = "waker"), doc = "`PipeBuf::register_rd_waker`")]
+//! .  Without this feature there is no overhead.
+//!
+//! # Debugging
+//!
+//! With the `poison` feature, free and reclaimed space in a
+//! [`PipeBuf`] may be filled with a recognizable value, to catch
+//! components that read space before writing it, or that hold on to
+//! stale data.  See
+#![cfg_attr(feature = "poison", doc = "[`PipeBuf::set_poison`]")]
+#![cfg_attr(not(feature = "poison"), doc = "`PipeBuf::set_poison`")]
+//! .  This is intended for tests only, and without the feature there
+//! is no overhead.
+//!
 //! # Using this as a dependency ... or not
 //!
 //! This crate currently depends on no other crates, and aims to
@@ -451,22 +478,46 @@ compile_error!("Both feature 'std' and feature 'static' cannot be enabled at the
 compile_error!("Both feature 'alloc' and feature 'static' cannot be enabled at the same time");
 
 mod buf;
-pub use buf::{PBufState, PBufTrip, PipeBuf};
+pub use buf::{PBufReady, PBufRef, PBufState, PBufTrip, PipeBuf};
 
 mod wr;
-pub use wr::PBufWr;
+#[cfg(feature = "std")]
+pub use wr::PBufTryWriter;
+pub use wr::{PBufTxn, PBufWr};
 
 mod rd;
-pub use rd::PBufRd;
+pub use rd::{PBufDrain, PBufRd};
 
 mod pair;
 pub use pair::{PBufRdWr, PipeBufPair};
 
+mod debug;
+pub use debug::PBufDump;
+
+mod fill;
+pub use fill::{PBufDrainer, PBufFiller};
+
+mod cursor;
+pub use cursor::PBufCursor;
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+mod pool;
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub use pool::PipeBufPool;
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+mod tripset;
+#[cfg(any(feature = "std", feature = "alloc"))]
+pub use tripset::PBufTripSet;
+
 /// Form a tuple of tripwire values
 ///
 /// This is intended to be used to create a tuple of [`PBufTrip`]
 /// values both before and after an operation.  The tuples can then be
-/// compared to see whether there was any change.
+/// compared to see whether there was any change.  Arguments may be
+/// anything with a `tripwire()` method, i.e. [`PipeBuf`], [`PBufRd`]
+/// and [`PBufWr`], and also [`PipeBufPair`] and [`PBufRdWr`] which
+/// give the tripwire values of both directions.
 ///
 #[cfg_attr(
     any(feature = "std", feature = "alloc"),
@@ -477,9 +528,10 @@ pub use pair::{PBufRdWr, PipeBufPair};
 # let p2 = PipeBuf::<u8>::new();
 # let p3 = PipeBuf::<u8>::new();
 # let p4 = PipeBuf::<u8>::new();
-let before = tripwire!(p1, p2, p3, p4);
-// some operation on p1/p2/p3/p4 ...
-let after = tripwire!(p1, p2, p3, p4);
+# let pair = pipebuf::PipeBufPair::<u8>::new();
+let before = tripwire!(p1, p2, p3, p4, pair);
+// some operation on p1/p2/p3/p4/pair ...
+let after = tripwire!(p1, p2, p3, p4, pair);
 let activity = before != after;
 ```
 "
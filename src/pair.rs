@@ -14,20 +14,41 @@ use super::{PBufRd, PBufTrip, PBufWr, PipeBuf};
 /// upper/lower is the most helpful terminology, but left/right is
 /// offered as an alternative.
 ///
-pub struct PipeBufPair<T: 'static = u8> {
+/// Normally both pipes carry the same element type `T`, but a
+/// different type `U` may be used for the upwards-flowing pipe, for
+/// example for a pipeline that passes raw bytes down and decoded
+/// `char` values up.  In that case, create the pair from its two
+/// buffers:
+///
+#[cfg_attr(
+    any(feature = "std", feature = "alloc"),
+    doc = "
+```
+# use pipebuf::{PipeBuf, PipeBufPair};
+let mut pair: PipeBufPair<u8, char> = PipeBufPair {
+    down: PipeBuf::new(),
+    up: PipeBuf::new(),
+};
+pair.upper().wr.append(b\"ab\");
+pair.lower().wr.append(&['a', 'b']);
+```
+"
+)]
+pub struct PipeBufPair<T: 'static = u8, U: 'static = T> {
     /// Downwards-flowing pipe
     pub down: PipeBuf<T>,
     /// Upwards-flowing pipe
-    pub up: PipeBuf<T>,
+    pub up: PipeBuf<U>,
 }
 
 impl<T: Copy + Default + 'static> PipeBufPair<T> {
-    /// Create a new empty bidirectional pipe
+    /// Create a new empty bidirectional pipe.  This is a `const`
+    /// function.
     #[cfg(any(feature = "std", feature = "alloc"))]
     #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
     #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
     #[inline]
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             down: PipeBuf::new(),
             up: PipeBuf::new(),
@@ -63,21 +84,23 @@ impl<T: Copy + Default + 'static> PipeBufPair<T> {
     }
 
     /// Create a new bidirectional pipe buffer backed by two regions
-    /// of static memory
+    /// of static memory.  This is a `const` function.
     #[cfg(feature = "static")]
     #[cfg_attr(docsrs, doc(cfg(feature = "static")))]
     #[inline]
-    pub fn new_static(down_buf: &'static mut [T], up_buf: &'static mut [T]) -> Self {
+    pub const fn new_static(down_buf: &'static mut [T], up_buf: &'static mut [T]) -> Self {
         Self {
             down: PipeBuf::new_static(down_buf),
             up: PipeBuf::new_static(up_buf),
         }
     }
+}
 
+impl<T: Copy + Default + 'static, U: Copy + Default + 'static> PipeBufPair<T, U> {
     /// Get the references for reading and writing the stream from the
     /// "upper" end
     #[inline]
-    pub fn upper(&mut self) -> PBufRdWr<'_, T> {
+    pub fn upper(&mut self) -> PBufRdWr<'_, U, T> {
         PBufRdWr {
             rd: self.up.rd(),
             wr: self.down.wr(),
@@ -87,7 +110,7 @@ impl<T: Copy + Default + 'static> PipeBufPair<T> {
     /// Get the references for reading and writing the stream from the
     /// "lower" end
     #[inline]
-    pub fn lower(&mut self) -> PBufRdWr<'_, T> {
+    pub fn lower(&mut self) -> PBufRdWr<'_, T, U> {
         PBufRdWr {
             rd: self.down.rd(),
             wr: self.up.wr(),
@@ -99,7 +122,7 @@ impl<T: Copy + Default + 'static> PipeBufPair<T> {
     /// readable, and actually this is the same as
     /// [`PipeBufPair::upper`].
     #[inline]
-    pub fn left(&mut self) -> PBufRdWr<'_, T> {
+    pub fn left(&mut self) -> PBufRdWr<'_, U, T> {
         self.upper()
     }
 
@@ -108,7 +131,7 @@ impl<T: Copy + Default + 'static> PipeBufPair<T> {
     /// readable, and actually this is the same as
     /// [`PipeBufPair::lower`].
     #[inline]
-    pub fn right(&mut self) -> PBufRdWr<'_, T> {
+    pub fn right(&mut self) -> PBufRdWr<'_, T, U> {
         self.lower()
     }
 
@@ -129,6 +152,23 @@ impl<T: Copy + Default + 'static> PipeBufPair<T> {
         self.down.reset_and_zero();
         self.up.reset_and_zero();
     }
+
+    /// Generate tripwire values for both the `down` and `up` pipes.
+    /// See [`PBufTrip`] for more details.  This may also be used with
+    /// the [`tripwire!`] macro.
+    ///
+    /// [`tripwire!`]: macro.tripwire.html
+    #[inline]
+    pub fn tripwire(&self) -> (PBufTrip, PBufTrip) {
+        (self.down.tripwire(), self.up.tripwire())
+    }
+
+    /// Test whether there has been a change to either pipe since the
+    /// tripwire values provided were obtained.  See [`PBufTrip`].
+    #[inline]
+    pub fn is_tripped(&self, trip: (PBufTrip, PBufTrip)) -> bool {
+        self.tripwire() != trip
+    }
 }
 
 #[cfg(any(feature = "std", feature = "alloc"))]
@@ -146,22 +186,23 @@ impl<T: Copy + Default + 'static> Default for PipeBufPair<T> {
 /// [`PipeBufPair::lower`] calls, or equivalently
 /// [`PipeBufPair::left`] and [`PipeBufPair::right`].  Reborrow it
 /// using [`PBufRdWr::reborrow`], or by reborrowing the members
-/// individually.
-pub struct PBufRdWr<'a, T: 'static = u8> {
+/// individually.  `T` is the element type of the incoming pipe, and
+/// `U` that of the outgoing pipe, which is normally the same.
+pub struct PBufRdWr<'a, T: 'static = u8, U: 'static = T> {
     /// Consumer reference for the incoming pipe
     pub rd: PBufRd<'a, T>,
     /// Producer reference for the outgoing pipe
-    pub wr: PBufWr<'a, T>,
+    pub wr: PBufWr<'a, U>,
 }
 
-impl<'a, T: Copy + Default + 'static> PBufRdWr<'a, T> {
+impl<'a, T: Copy + Default + 'static, U: Copy + Default + 'static> PBufRdWr<'a, T, U> {
     /// Create new references from these, reborrowing them.  Thanks to
     /// the borrow checker, the original references will be
     /// inaccessible until the returned references' lifetimes end.
     /// The cost is just a couple of pointer copies, just as for
     /// `&mut` reborrowing.
     #[inline(always)]
-    pub fn reborrow<'b, 'r>(&'r mut self) -> PBufRdWr<'b, T>
+    pub fn reborrow<'b, 'r>(&'r mut self) -> PBufRdWr<'b, T, U>
     where
         'a: 'b,
         'r: 'b,
@@ -178,4 +219,11 @@ impl<'a, T: Copy + Default + 'static> PBufRdWr<'a, T> {
     pub fn tripwire(&self) -> (PBufTrip, PBufTrip) {
         (self.rd.tripwire(), self.wr.tripwire())
     }
+
+    /// Test whether there has been a change to either pipe since the
+    /// tripwire values provided were obtained.  See [`PBufTrip`].
+    #[inline]
+    pub fn is_tripped(&self, trip: (PBufTrip, PBufTrip)) -> bool {
+        self.tripwire() != trip
+    }
 }
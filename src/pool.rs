@@ -0,0 +1,142 @@
+use super::PipeBuf;
+
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+extern crate alloc;
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
+
+/// Pool of reusable pipe buffers
+///
+/// Servers which create one or more [`PipeBuf`] instances per
+/// connection can use this to avoid reallocating buffers for every
+/// new connection.  Use [`PipeBufPool::get`] to obtain a buffer and
+/// [`PipeBufPool::put`] to return it to the pool once processing is
+/// complete.  Returned buffers are reset, and optionally zeroed, so
+/// that they are ready for reuse.
+///
+/// The pool keeps some simple statistics: the number of buffers
+/// currently handed out, and the high-water mark of that figure,
+/// which may help in sizing the pool.
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct PipeBufPool<T: 'static = u8> {
+    free: Vec<PipeBuf<T>>,
+    capacity: usize,
+    fixed_capacity: bool,
+    zero: bool,
+    in_use: usize,
+    high_water: usize,
+}
+
+impl<T: Copy + Default + 'static> PipeBufPool<T> {
+    /// Create a new empty pool which will create variable-capacity
+    /// buffers with the given initial capacity
+    #[inline]
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            free: Vec::new(),
+            capacity: cap,
+            fixed_capacity: false,
+            zero: false,
+            in_use: 0,
+            high_water: 0,
+        }
+    }
+
+    /// Create a new empty pool which will create buffers with the
+    /// given fixed capacity.  See [`PipeBuf::with_fixed_capacity`].
+    #[inline]
+    pub fn with_fixed_capacity(cap: usize) -> Self {
+        Self {
+            fixed_capacity: true,
+            ..Self::with_capacity(cap)
+        }
+    }
+
+    /// Select whether buffers are zeroed when they are returned to
+    /// the pool, using [`PipeBuf::reset_and_zero`].  By default they
+    /// are only reset using [`PipeBuf::reset`].  Zeroing is
+    /// recommended if buffers may contain sensitive data, to avoid
+    /// that data leaking between connections.
+    #[inline]
+    pub fn set_zero(&mut self, zero: bool) {
+        self.zero = zero;
+    }
+
+    /// Get a buffer from the pool, or create a new one if the pool is
+    /// empty.  The buffer is in its initial state, i.e. `Open` and
+    /// empty.
+    #[inline]
+    pub fn get(&mut self) -> PipeBuf<T> {
+        self.in_use += 1;
+        self.high_water = self.high_water.max(self.in_use);
+        match self.free.pop() {
+            Some(pb) => pb,
+            None if self.fixed_capacity => PipeBuf::with_fixed_capacity(self.capacity),
+            None => PipeBuf::with_capacity(self.capacity),
+        }
+    }
+
+    /// Return a buffer to the pool.  It is reset (and zeroed if
+    /// selected with [`PipeBufPool::set_zero`]) before being stored.
+    /// Per-buffer settings such as [`PipeBuf::set_writable`],
+    /// [`PipeBuf::set_relaxed`], [`PipeBuf::set_history`],
+    /// [`PipeBuf::set_wipe_on_consume`] and
+    /// [`PipeBuf::set_growth_hook`] are also restored to their
+    /// defaults, so that the next user gets a buffer just like a new
+    /// one.  A variable-capacity buffer that has grown is shrunk back
+    /// to the configured capacity, so that one burst of data doesn't
+    /// pin its memory forever.  A buffer that doesn't match the
+    /// pool's configuration (fixed or variable capacity, and the
+    /// fixed capacity itself) is dropped instead of being stored.
+    #[inline]
+    pub fn put(&mut self, mut pb: PipeBuf<T>) {
+        self.in_use = self.in_use.saturating_sub(1);
+        if self.zero {
+            pb.reset_and_zero();
+        } else {
+            pb.reset();
+        }
+        if pb.fixed_capacity != self.fixed_capacity
+            || (self.fixed_capacity && pb.capacity() != self.capacity)
+        {
+            return;
+        }
+        pb.shrink_to(self.capacity);
+        pb.ext = None;
+        pb.paused = false;
+        pb.relaxed = false;
+        #[cfg(feature = "poison")]
+        {
+            pb.poison = None;
+        }
+        self.free.push(pb);
+    }
+
+    /// Get the number of buffers currently handed out by the pool and
+    /// not yet returned
+    #[inline]
+    pub fn in_use(&self) -> usize {
+        self.in_use
+    }
+
+    /// Get the maximum number of buffers that have been handed out at
+    /// the same time
+    #[inline]
+    pub fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    /// Get the number of buffers held in the pool ready for reuse
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Drop all the buffers held in the pool to release their memory.
+    /// Statistics are unaffected.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.free.clear();
+    }
+}
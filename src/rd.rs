@@ -64,21 +64,144 @@ impl<'a, T: Copy + Default + 'static> PBufRd<'a, T> {
         &mut self.pb.data[self.pb.rd..self.pb.wr]
     }
 
+    /// Get the current contents of the buffer as an array of slices,
+    /// which when concatenated give the same data as
+    /// [`PBufRd::data`].  Currently the second slice is always empty,
+    /// since the storage is contiguous, but consumer code written
+    /// against this form (e.g. for vectored I/O) would remain optimal
+    /// if the storage strategy ever changed.
+    #[inline(always)]
+    pub fn data_vectored(&self) -> [&[T]; 2] {
+        [self.data(), &[]]
+    }
+
+    /// Get the retained history of consumed data, i.e. up to the last
+    /// N bytes consumed, where N was configured using
+    /// [`PipeBuf::set_history`].  The slice is empty if no history is
+    /// being kept.  Fewer than N bytes are returned if fewer than
+    /// that have been consumed since the buffer was created or reset.
+    #[inline]
+    pub fn history(&self) -> &[T] {
+        &self.pb.data[self.pb.rd - self.pb.retained()..self.pb.rd]
+    }
+
+    /// Un-consume the last `len` bytes consumed, making them visible
+    /// again at the start of [`PBufRd::data`].  This is only possible
+    /// within the retained history window (see
+    /// [`PBufRd::history`]).  This allows a parser to back up, for
+    /// example after a failed speculative parse.
+    ///
+    /// Note that this increases the tripwire value, unlike other
+    /// consumer operations, so a tripwire comparison may miss changes
+    /// if data is both rewound and consumed between two tripwire
+    /// values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is greater than the length of the retained
+    /// history, unless relaxed mode is enabled (see
+    /// [`PipeBuf::set_relaxed`])
+    #[inline]
+    #[track_caller]
+    pub fn rewind(&mut self, mut len: usize) {
+        if len > self.history().len() {
+            if !self.pb.relaxed_overflow() {
+                panic_rewind_overflow();
+            }
+            len = self.history().len();
+        }
+        self.pb.rd -= len;
+        if let Some(ref mut ext) = self.pb.ext {
+            ext.retained -= len;
+        }
+    }
+
     /// Indicate that `len` bytes should be marked as consumed from
     /// the start of the buffer.  They will be discarded and will no
     /// longer be visible through this interface.
     ///
     /// # Panics
     ///
-    /// Panics if `len` is greater than the number bytes in the buffer
+    /// Panics if `len` is greater than the number bytes in the
+    /// buffer, unless relaxed mode is enabled (see
+    /// [`PipeBuf::set_relaxed`])
     #[inline]
     #[track_caller]
-    pub fn consume(&mut self, len: usize) {
-        let rd = self.pb.rd + len;
-        if rd > self.pb.wr {
-            panic_consume_overflow();
+    pub fn consume(&mut self, mut len: usize) {
+        if len > self.len() {
+            if !self.pb.relaxed_overflow() {
+                panic_consume_overflow();
+            }
+            len = self.len();
         }
-        self.pb.rd = rd;
+        self.pb.set_rd(self.pb.rd + len);
+    }
+
+    /// Discard all the data held in the buffer.  This may be useful
+    /// for glue code when a session is being aborted and the queued
+    /// data will never be used.  Unlike consuming, this doesn't add
+    /// the data to the history window (see [`PBufRd::history`]), and
+    /// any history already retained is dropped too.
+    #[inline]
+    pub fn discard_all(&mut self) {
+        let start = self.pb.rd - self.pb.retained();
+        if self.pb.wiping() {
+            self.pb.wipe_range(start, self.pb.wr);
+        }
+        if let Some(ref mut ext) = self.pb.ext {
+            ext.retained = 0;
+        }
+        if self.pb.wr > self.pb.rd {
+            self.pb.rd = self.pb.wr;
+            self.pb.wake_wr();
+        }
+    }
+
+    /// Discard `len` bytes from the end of the data held in the
+    /// buffer, i.e. the most recently written data.  The data at the
+    /// start of the buffer is unaffected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is greater than the number bytes in the
+    /// buffer, unless relaxed mode is enabled (see
+    /// [`PipeBuf::set_relaxed`])
+    #[inline]
+    #[track_caller]
+    pub fn discard_tail(&mut self, mut len: usize) {
+        if len > self.len() {
+            if !self.pb.relaxed_overflow() {
+                panic_consume_overflow();
+            }
+            len = self.len();
+        }
+        self.pb.wr -= len;
+        if self.pb.wiping() {
+            self.pb.wipe_range(self.pb.wr, self.pb.wr + len);
+        }
+        self.pb.wake_wr();
+    }
+
+    /// Consume the longest run of bytes from the start of the buffer
+    /// which match the given predicate, and return the number of
+    /// bytes consumed.  For example this may be used to skip
+    /// whitespace or to scan over a run of digits.
+    #[inline]
+    pub fn consume_while(&mut self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        let data = self.data();
+        let len = data.iter().position(|v| !pred(v)).unwrap_or(data.len());
+        self.pb.set_rd(self.pb.rd + len);
+        len
+    }
+
+    /// Get an iterator which returns the bytes in the buffer one by
+    /// one, consuming each one as it is returned.  Iteration stops
+    /// when the buffer is empty.  Any bytes not iterated over remain
+    /// in the buffer.  This may be convenient for code that processes
+    /// data one element at a time, such as a simple tokenizer.
+    #[inline]
+    pub fn drain_iter(&mut self) -> PBufDrain<'_, T> {
+        PBufDrain { pb: &mut *self.pb }
     }
 
     /// Get the number of bytes held in the buffer
@@ -167,16 +290,55 @@ impl<'a, T: Copy + Default + 'static> PBufRd<'a, T> {
 
     /// Forward all the data found in this pipe to another pipe.  Also
     /// forwards "push" and EOF indications.
+    ///
+    /// If both pipes are variable-capacity and the destination is
+    /// empty, then the backing memory of the two pipes is swapped
+    /// instead of copying the data, which makes relaying large
+    /// amounts of data almost free.  This is not done if either pipe
+    /// has a growth hook (see
+    #[cfg_attr(
+        any(feature = "std", feature = "alloc"),
+        doc = "[`PipeBuf::set_growth_hook`]),"
+    )]
+    #[cfg_attr(
+        not(any(feature = "std", feature = "alloc")),
+        doc = "`PipeBuf::set_growth_hook`),"
+    )]
+    /// since the swap would change its capacity without the hook
+    /// being consulted.
     pub fn forward(&mut self, mut dest: PBufWr<'_, T>) {
         if dest.is_eof() {
             return;
         }
 
-        let data = self.data();
-        let len = data.len();
-        dest.space(len).copy_from_slice(data);
-        dest.commit(len);
-        self.consume(len);
+        #[cfg(any(feature = "std", feature = "alloc"))]
+        let swap = !self.is_empty()
+            && dest.pb.rd == dest.pb.wr
+            && !self.pb.fixed_capacity
+            && !dest.pb.fixed_capacity
+            && !self.pb.keeps_history()
+            && !dest.pb.keeps_history()
+            && !self.pb.has_growth_hook()
+            && !dest.pb.has_growth_hook();
+        #[cfg(not(any(feature = "std", feature = "alloc")))]
+        let swap = false;
+
+        if swap {
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            core::mem::swap(&mut self.pb.data, &mut dest.pb.data);
+            dest.pb.rd = self.pb.rd;
+            dest.pb.wr = self.pb.wr;
+            self.pb.rd = 0;
+            self.pb.wr = 0;
+            self.pb.wake_wr();
+            dest.pb.wake_rd();
+        } else {
+            let data = self.data();
+            let len = data.len();
+            dest.space(len).copy_from_slice(data);
+            dest.commit(len);
+            self.consume(len);
+        }
 
         if self.consume_push() {
             dest.push();
@@ -249,9 +411,65 @@ impl<'a> std::io::Read for PBufRd<'a, u8> {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<'a> std::io::BufRead for PBufRd<'a, u8> {
+    /// Get the unread data in the pipe-buffer directly, without
+    /// copying.  See the `BufRead` implementation on [`PipeBuf`] for
+    /// the possible returns.
+    fn fill_buf(&mut self) -> Result<&[u8], std::io::Error> {
+        self.pb.fill_buf()
+    }
+
+    /// Consume data previously returned by `fill_buf`.  Panics if
+    /// `amt` is greater than the number of bytes available.
+    #[track_caller]
+    fn consume(&mut self, amt: usize) {
+        PBufRd::consume(self, amt);
+    }
+}
+
+/// Consuming iterator over the contents of a [`PipeBuf`]
+///
+/// Obtain this using [`PBufRd::drain_iter`].  Each element returned
+/// is consumed from the buffer.
+pub struct PBufDrain<'a, T: 'static = u8> {
+    pb: &'a mut PipeBuf<T>,
+}
+
+impl<'a, T: Copy + Default + 'static> Iterator for PBufDrain<'a, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.pb.rd < self.pb.wr {
+            let v = self.pb.data[self.pb.rd];
+            self.pb.set_rd(self.pb.rd + 1);
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.pb.wr - self.pb.rd;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: Copy + Default + 'static> ExactSizeIterator for PBufDrain<'a, T> {}
+
 #[inline(never)]
 #[cold]
 #[track_caller]
 fn panic_consume_overflow() -> ! {
     panic!("Illegal to consume more PipeBuf bytes than are available");
 }
+
+#[inline(never)]
+#[cold]
+#[track_caller]
+fn panic_rewind_overflow() -> ! {
+    panic!("Illegal to rewind PipeBuf further than the retained history");
+}
@@ -0,0 +1,94 @@
+use super::PBufTrip;
+
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+extern crate alloc;
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
+
+/// Set of tripwire values for change-tracking across many buffers
+///
+/// For a large processing network, forming tuples with the
+/// [`tripwire!`] macro becomes unwieldy.  Instead the glue code can
+/// number its buffers and keep their last-seen [`PBufTrip`] values in
+/// a `PBufTripSet`, which can then report which of the buffers have
+/// changed since they were last checked.  The tripwire values may
+/// come from [`PipeBuf::tripwire`], [`PBufRd::tripwire`] or
+/// [`PBufWr::tripwire`].  The same caveats apply as for
+/// [`PBufTrip`]: an operation that both produces and consumes data on
+/// the same buffer may not be detected.
+///
+/// ```
+///# use pipebuf::{PBufTripSet, PipeBuf};
+/// let mut p0 = PipeBuf::<u8>::new();
+/// let mut p1 = PipeBuf::<u8>::new();
+/// let mut set = PBufTripSet::new();
+/// let mut changed = Vec::new();
+/// set.update_all([p0.tripwire(), p1.tripwire()], &mut changed);
+/// assert_eq!(vec![0, 1], changed);
+/// changed.clear();
+/// p1.wr().append(b"data");
+/// set.update_all([p0.tripwire(), p1.tripwire()], &mut changed);
+/// assert_eq!(vec![1], changed);
+/// ```
+///
+/// [`tripwire!`]: macro.tripwire.html
+/// [`PipeBuf::tripwire`]: struct.PipeBuf.html#method.tripwire
+/// [`PBufRd::tripwire`]: struct.PBufRd.html#method.tripwire
+/// [`PBufWr::tripwire`]: struct.PBufWr.html#method.tripwire
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[derive(Default)]
+pub struct PBufTripSet {
+    trips: Vec<Option<PBufTrip>>,
+}
+
+impl PBufTripSet {
+    /// Create a new empty set
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the tripwire value for the buffer numbered `index`, and
+    /// return `true` if it differs from the value previously recorded
+    /// for that buffer.  The first time a buffer is recorded it is
+    /// reported as changed.
+    #[inline]
+    pub fn update(&mut self, index: usize, trip: PBufTrip) -> bool {
+        if index >= self.trips.len() {
+            self.trips.resize(index + 1, None);
+        }
+        self.trips[index].replace(trip) != Some(trip)
+    }
+
+    /// Test whether the tripwire value for the buffer numbered
+    /// `index` differs from the value previously recorded, without
+    /// recording the new value
+    #[inline]
+    pub fn is_tripped(&self, index: usize, trip: PBufTrip) -> bool {
+        self.trips.get(index).copied().flatten() != Some(trip)
+    }
+
+    /// Record the tripwire values for buffers numbered from 0
+    /// upwards, and add the indices of those that have changed since
+    /// they were last recorded to `changed`, for example a `Vec` that
+    /// the caller keeps and clears between calls.  All the values are
+    /// recorded before this returns.
+    pub fn update_all(
+        &mut self,
+        trips: impl IntoIterator<Item = PBufTrip>,
+        changed: &mut impl Extend<usize>,
+    ) {
+        for (i, trip) in trips.into_iter().enumerate() {
+            if self.update(i, trip) {
+                changed.extend(Some(i));
+            }
+        }
+    }
+
+    /// Forget all the recorded tripwire values
+    #[inline]
+    pub fn clear(&mut self) {
+        self.trips.clear();
+    }
+}
@@ -1,7 +1,7 @@
 use super::{PBufState, PBufTrip, PipeBuf};
 
 #[cfg(feature = "std")]
-use std::io::{ErrorKind, Read};
+use std::io::{ErrorKind, IoSliceMut, Read};
 
 /// Producer reference to a [`PipeBuf`]
 ///
@@ -72,7 +72,7 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
     #[inline]
     #[track_caller]
     pub fn space(&mut self, reserve: usize) -> &mut [T] {
-        if self.pb.rd == self.pb.wr {
+        if self.pb.rd == self.pb.wr && self.pb.retained() == 0 {
             self.pb.rd = 0;
             self.pb.wr = 0;
         }
@@ -81,7 +81,14 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
             self.make_space(reserve);
         }
 
-        &mut self.pb.data[self.pb.wr..self.pb.wr + reserve]
+        #[cfg(feature = "poison")]
+        let poison = self.pb.poison;
+        let space = &mut self.pb.data[self.pb.wr..self.pb.wr + reserve];
+        #[cfg(feature = "poison")]
+        if let Some(v) = poison {
+            space.fill(v);
+        }
+        space
     }
 
     /// Get a reference to a mutable slice of `reserve` bytes of free
@@ -95,11 +102,15 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
     /// assumptions about this data.
     ///
     /// Returns `None` if there is not enough free space available in
-    /// a fixed-capacity [`PipeBuf`].
+    /// a fixed-capacity [`PipeBuf`], or if writing has been paused
+    /// by the glue code using [`PipeBuf::set_writable`].
     #[inline]
     #[track_caller]
     pub fn try_space(&mut self, reserve: usize) -> Option<&mut [T]> {
-        if self.pb.rd == self.pb.wr {
+        if self.pb.paused && reserve > 0 {
+            return None;
+        }
+        if self.pb.rd == self.pb.wr && self.pb.retained() == 0 {
             self.pb.rd = 0;
             self.pb.wr = 0;
         }
@@ -107,7 +118,14 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
         if self.pb.wr + reserve > self.pb.data.len() && !self.try_make_space(reserve) {
             None
         } else {
-            Some(&mut self.pb.data[self.pb.wr..self.pb.wr + reserve])
+            #[cfg(feature = "poison")]
+            let poison = self.pb.poison;
+            let space = &mut self.pb.data[self.pb.wr..self.pb.wr + reserve];
+            #[cfg(feature = "poison")]
+            if let Some(v) = poison {
+                space.fill(v);
+            }
+            Some(space)
         }
     }
 
@@ -120,7 +138,7 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
     #[track_caller]
     fn make_space(&mut self, reserve: usize) {
         if !self.make_space_aux(reserve) {
-            panic!("Not enough space available in fixed-capacity PipeBuf");
+            panic!("Not enough space available in PipeBuf: fixed capacity or growth vetoed");
         }
     }
 
@@ -134,13 +152,17 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
     #[inline(always)]
     fn make_space_aux(&mut self, _reserve: usize) -> bool {
         // Caller guarantees that if .rd == .wr, then now both .rd and
-        // .wr will be zero, so if .rd > 0 then there is something to
-        // copy down
-        debug_assert!(self.pb.rd != self.pb.wr || self.pb.rd == 0);
-        if self.pb.rd > 0 {
-            self.pb.data.copy_within(self.pb.rd..self.pb.wr, 0);
-            self.pb.wr -= self.pb.rd;
-            self.pb.rd = 0;
+        // .wr will be zero (unless history is being kept), so if .rd >
+        // 0 then there is something to copy down
+        debug_assert!(self.pb.rd != self.pb.wr || self.pb.rd == 0 || self.pb.retained() > 0);
+        self.pb.compact();
+
+        // History is best-effort, so give up as much of the oldest
+        // retained data as necessary before failing or growing
+        let short = (self.pb.wr + _reserve).saturating_sub(self.pb.data.len());
+        if short > 0 && self.pb.retained() > 0 {
+            self.pb.drop_history(short);
+            self.pb.compact();
         }
 
         #[cfg(any(feature = "std", feature = "alloc"))]
@@ -148,9 +170,28 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
             if self.pb.fixed_capacity {
                 return false;
             }
-            let cap = (self.pb.wr + _reserve).max(_reserve * 2);
-            self.pb.data.reserve(cap - self.pb.data.len());
-            self.pb.data.resize(self.pb.data.capacity(), T::default());
+            // Grow geometrically, as `Vec` would, to keep the cost of
+            // repeated appends linear.  The new capacity is decided
+            // here, so that the hook is told the real value.
+            let cap = (self.pb.wr + _reserve)
+                .max(_reserve * 2)
+                .max(self.pb.data.len() * 2);
+            if let Some(hook) = self
+                .pb
+                .ext
+                .as_mut()
+                .and_then(|ext| ext.growth_hook.as_mut())
+            {
+                if !hook(self.pb.data.len(), cap) {
+                    return false;
+                }
+            }
+            if self.pb.wiping() {
+                self.pb.realloc_wiped(cap);
+            } else {
+                self.pb.data.reserve_exact(cap - self.pb.data.len());
+                self.pb.data.resize(cap, T::default());
+            }
         }
 
         #[cfg(feature = "static")]
@@ -169,19 +210,30 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
     ///
     /// Panics if data is written to the stream after it has been
     /// marked as closed or aborted.  May panic if more data is
-    /// committed than the space that was reserved.
+    /// committed than the space that was reserved.  In relaxed mode
+    /// (see [`PipeBuf::set_relaxed`]) these cases don't panic, but
+    /// instead the data is dropped or the length is clamped.
     #[inline]
     #[track_caller]
-    pub fn commit(&mut self, len: usize) {
+    pub fn commit(&mut self, mut len: usize) {
         if self.is_eof() {
-            panic_closed_pipebuf();
+            if !self.pb.relaxed_overflow() {
+                panic_closed_pipebuf();
+            }
+            return;
         }
 
-        let wr = self.pb.wr + len;
-        if wr > self.pb.data.len() {
-            panic_commit_overflow();
+        let space = self.pb.data.len() - self.pb.wr;
+        if len > space {
+            if !self.pb.relaxed_overflow() {
+                panic_commit_overflow();
+            }
+            len = space;
+        }
+        if len > 0 {
+            self.pb.wr += len;
+            self.pb.wake_rd();
         }
-        self.pb.wr = wr;
     }
 
     /// Return the amount of free space left in the underlying
@@ -214,16 +266,13 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
     /// So this call can be used as part of a backpressure-aware
     /// processing step by only consuming sufficient data to create
     /// [`PBufWr::free_space`] elements of output.
+    ///
+    /// If writing has been paused by the glue code using
+    /// [`PipeBuf::set_writable`], then this returns `Some(0)`
+    /// whatever the capacity of the buffer.
     #[inline]
     pub fn free_space(&self) -> Option<usize> {
-        #[cfg(any(feature = "std", feature = "alloc"))]
-        return self
-            .pb
-            .fixed_capacity
-            .then_some(self.pb.data.len() - (self.pb.wr - self.pb.rd));
-
-        #[cfg(not(any(feature = "std", feature = "alloc")))]
-        return Some(self.pb.data.len() - (self.pb.wr - self.pb.rd));
+        self.pb.free_space()
     }
 
     /// Set the "push" state on the buffer, which the consumer may use
@@ -232,6 +281,7 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
     pub fn push(&mut self) {
         if self.pb.state == PBufState::Open {
             self.pb.state = PBufState::Push;
+            self.pb.wake_rd();
         }
     }
 
@@ -250,6 +300,88 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
         self.commit(len);
     }
 
+    /// Append items from an iterator for as long as there is space
+    /// available.  Space is reserved according to the iterator's
+    /// lower size bound, as for the `Extend` implementation, but
+    /// using [`PBufWr::try_space`], so a full fixed-capacity buffer,
+    /// a vetoed growth (see
+    #[cfg_attr(
+        any(feature = "std", feature = "alloc"),
+        doc = "[`PipeBuf::set_growth_hook`])"
+    )]
+    #[cfg_attr(
+        not(any(feature = "std", feature = "alloc")),
+        doc = "`PipeBuf::set_growth_hook`)"
+    )]
+    /// or paused writing (see [`PipeBuf::set_writable`]) stops the
+    /// operation instead of panicking.  Returns `true` if the
+    /// iterator was exhausted, or `false` if space ran out first, in
+    /// which case the items not yet written are left in the iterator,
+    /// ready for a retry once the consumer has freed space.  If space
+    /// runs out at exactly the point where the iterator ends, `false`
+    /// may be returned even though there is nothing left to write,
+    /// unless the iterator's upper size bound shows that it is
+    /// exhausted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if data is written to the pipe buffer after it has been
+    /// marked as closed or aborted.
+    #[track_caller]
+    pub fn try_extend(&mut self, iter: &mut impl Iterator<Item = T>) -> bool {
+        loop {
+            let (lower, upper) = iter.size_hint();
+            if upper == Some(0) {
+                return true;
+            }
+            let hint = lower.max(1);
+            let reserve = match self.free_space() {
+                Some(free) => hint.min(free),
+                None => hint,
+            };
+            let space = match self.try_space(reserve) {
+                Some(space) if reserve > 0 => space,
+                _ => return false,
+            };
+            let mut len = 0;
+            for (dst, v) in space.iter_mut().zip(&mut *iter) {
+                *dst = v;
+                len += 1;
+            }
+            self.commit(len);
+            if len < reserve {
+                return true;
+            }
+        }
+    }
+
+    /// Append all the unread data in another pipe buffer to this one,
+    /// draining the other buffer.  If `forward_state` is `true` then
+    /// "push" and EOF indications are also moved across, as for
+    /// [`PBufRd::forward`].  Otherwise the other buffer's state is
+    /// left untouched, which allows many per-request buffers to be
+    /// joined into a single session output buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if data is written to the pipe buffer after it has been
+    /// marked as closed or aborted.  For fixed-capacity panics, see
+    /// [`PBufWr::space`].
+    ///
+    /// [`PBufRd::forward`]: crate::PBufRd::forward
+    #[inline]
+    #[track_caller]
+    pub fn append_pipebuf(&mut self, src: &mut PipeBuf<T>, forward_state: bool) {
+        if forward_state {
+            src.rd().forward(self.reborrow());
+        } else if !src.rd().is_empty() {
+            let mut rd = src.rd();
+            self.append(rd.data());
+            let len = rd.len();
+            rd.consume(len);
+        }
+    }
+
     /// Test whether end-of-file has already been indicated, either
     /// using [`PBufWr::close`] or [`PBufWr::abort`].  No more data
     /// should be written after EOF.
@@ -272,6 +404,35 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
             return;
         }
         self.pb.state = PBufState::Closing;
+        self.pb.wake_rd();
+    }
+
+    /// Append a final slice of data to the buffer and indicate
+    /// end-of-file with success, as a single operation.  If there is
+    /// not enough free space in a fixed-capacity buffer for the data,
+    /// then nothing is written, the buffer is not closed, and `false`
+    /// is returned.  This allows a component to retry later, rather
+    /// than risking closing the stream without its final record.
+    /// Returns `true` on success.
+    ///
+    /// If the stream is already closed or aborted then ignores this
+    /// call and returns `false`.
+    #[inline]
+    #[track_caller]
+    pub fn close_with_final(&mut self, data: &[T]) -> bool {
+        if self.is_eof() {
+            return false;
+        }
+        let len = data.len();
+        match self.try_space(len) {
+            None => false,
+            Some(space) => {
+                space.copy_from_slice(data);
+                self.commit(len);
+                self.close();
+                true
+            }
+        }
     }
 
     /// Indicate end-of-file with abort.  This is an EOF after some
@@ -292,6 +453,7 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
             return;
         }
         self.pb.state = PBufState::Aborting;
+        self.pb.wake_rd();
     }
 
     /// Write data to the buffer using a closure.  A mutable slice of
@@ -356,6 +518,31 @@ impl<'a, T: Copy + Default + 'static> PBufWr<'a, T> {
         len
     }
 
+    /// Start a write transaction.  Data may be written through the
+    /// returned [`PBufTxn`] in as many steps as necessary, but it will
+    /// only be kept if [`PBufTxn::commit`] is called.  If the
+    /// transaction is dropped without committing, then all the data
+    /// written and any EOF or "push" indicated since the start of the
+    /// transaction are discarded.  This allows a multi-part message
+    /// to be written all-or-nothing, for example if the encoder
+    /// discovers part-way through that there is not enough space.
+    ///
+    /// With the "waker" feature, the consumer's waker is not woken
+    /// for changes made within the transaction, since they might
+    /// still be rolled back.  Instead it is woken when the outermost
+    /// transaction commits, if anything has changed.
+    #[inline]
+    pub fn transaction(&mut self) -> PBufTxn<'_, T> {
+        PBufTxn {
+            len: self.pb.wr - self.pb.rd,
+            state: self.pb.state,
+            #[cfg(feature = "waker")]
+            outer: self.pb.set_in_txn(true),
+            wr: self.reborrow(),
+            done: false,
+        }
+    }
+
     /// Test whether the amount of data stored in the pipe-buffer
     /// exceeds the given limit in bytes.  It is preferred to not
     /// expose any information about the consumer-side of the
@@ -408,6 +595,129 @@ impl<'a> PBufWr<'a, u8> {
         }
         Ok(())
     }
+
+    /// Get `reserve` bytes of free space as an array of
+    /// `IoSliceMut`, suitable for passing directly to a vectored read
+    /// call such as `readv` or `recvmsg`, or to
+    /// `Read::read_vectored`.  Once the data has been read, commit
+    /// it using [`PBufWr::commit`].  Currently the second slice is
+    /// always empty, since the storage is contiguous, as for
+    /// [`PBufRd::data_vectored`].  The same panics apply as for
+    /// [`PBufWr::space`].
+    ///
+    /// [`PBufRd::data_vectored`]: struct.PBufRd.html#method.data_vectored
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[inline]
+    #[track_caller]
+    pub fn space_io_slices(&mut self, reserve: usize) -> [IoSliceMut<'_>; 2] {
+        [
+            IoSliceMut::new(self.space(reserve)),
+            IoSliceMut::new(&mut []),
+        ]
+    }
+
+    /// Get a `Write` implementation for this buffer which fails with
+    /// `ErrorKind::WriteZero` when a fixed-capacity buffer is full,
+    /// instead of panicking like the `Write` implementation on
+    /// [`PBufWr`] itself.  This allows serializers or formatters that
+    /// write to a `Write` to output directly into the pipe-buffer
+    /// with a clean failure path.  Use it within a
+    /// [`PBufWr::transaction`] to discard any partial output if the
+    /// write fails.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[inline]
+    pub fn try_writer(&mut self) -> PBufTryWriter<'_> {
+        PBufTryWriter {
+            wr: self.reborrow(),
+        }
+    }
+}
+
+/// Non-panicking `Write` implementation for a [`PipeBuf`]
+///
+/// Obtain this using [`PBufWr::try_writer`].
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub struct PBufTryWriter<'a> {
+    wr: PBufWr<'a, u8>,
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<'a> std::io::Write for PBufTryWriter<'a> {
+    /// Write as much data as will fit.  Returns an error of kind
+    /// `ErrorKind::WriteZero` if no data could be written.
+    fn write(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        let len = match self.wr.free_space() {
+            None => data.len(),
+            Some(free) => data.len().min(free),
+        };
+        if len == 0 && !data.is_empty() {
+            return Err(ErrorKind::WriteZero.into());
+        }
+        match self.wr.try_space(len) {
+            None => Err(ErrorKind::WriteZero.into()),
+            Some(space) => {
+                space.copy_from_slice(&data[..len]);
+                self.wr.commit(len);
+                Ok(len)
+            }
+        }
+    }
+
+    /// Flush sets the "push" state on the [`PipeBuf`]
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.wr.push();
+        Ok(())
+    }
+}
+
+impl<'a, T: Copy + Default + 'static> Extend<T> for PBufWr<'a, T> {
+    /// Append the items to the buffer.  Space is reserved according
+    /// to the iterator's lower size bound, so iterators that report
+    /// their length are written in one go.
+    ///
+    /// # Panics
+    ///
+    /// The same panics apply as for [`PBufWr::append`], i.e. if the
+    /// stream has been closed, or if a fixed-capacity buffer runs out
+    /// of space.  To handle running out of space, use
+    /// [`PBufWr::try_extend`] instead.
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter();
+        loop {
+            let reserve = iter.size_hint().0;
+            if reserve == 0 {
+                match iter.next() {
+                    None => break,
+                    Some(v) => self.append(&[v]),
+                }
+            } else {
+                let mut len = 0;
+                for (dst, v) in self.space(reserve).iter_mut().zip(&mut iter) {
+                    *dst = v;
+                    len += 1;
+                }
+                self.commit(len);
+                if len == 0 {
+                    // The size hint overstated the length
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, 'b, T: Copy + Default + 'static> Extend<&'b T> for PBufWr<'a, T> {
+    /// Append copies of the items to the buffer.  See the
+    /// `Extend<T>` implementation.
+    #[track_caller]
+    fn extend<I: IntoIterator<Item = &'b T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
 }
 
 #[cfg(feature = "std")]
@@ -424,6 +734,134 @@ impl<'a> std::io::Write for PBufWr<'a, u8> {
     }
 }
 
+/// Producer write transaction
+///
+/// Obtain this using [`PBufWr::transaction`].  Use [`PBufTxn::wr`] to
+/// write to the buffer, and then [`PBufTxn::commit`] to keep what was
+/// written.  If dropped without committing, the buffer is rolled back
+/// to the state it was in when the transaction started.
+///
+/// Transactions on several outputs can be used together to make the
+/// writes to all of them atomic, for example in a demultiplexer.
+/// Start a transaction on each output, write to each one using
+/// [`PBufWr::try_space`] or similar, and then commit all of them only
+/// if all the writes succeeded.  Otherwise dropping the transactions
+/// rolls back all the outputs.
+///
+#[cfg_attr(
+    any(feature = "std", feature = "alloc"),
+    doc = "
+```
+# use pipebuf::{PBufWr, PipeBuf};
+fn write_all(out1: &mut PBufWr, out2: &mut PBufWr, data: &[u8]) -> bool {
+    let mut t1 = out1.transaction();
+    let mut t2 = out2.transaction();
+    for mut wr in [t1.wr(), t2.wr()] {
+        match wr.try_space(data.len()) {
+            None => return false, // Both are rolled back
+            Some(space) => space.copy_from_slice(data),
+        }
+        wr.commit(data.len());
+    }
+    t1.commit();
+    t2.commit();
+    true
+}
+# let mut p1 = PipeBuf::with_fixed_capacity(10);
+# let mut p2 = PipeBuf::with_fixed_capacity(5);
+# assert!(!write_all(&mut p1.wr(), &mut p2.wr(), b\"123456\"));
+# assert!(p1.rd().is_empty());
+# assert!(write_all(&mut p1.wr(), &mut p2.wr(), b\"12345\"));
+# assert_eq!(p1.rd().data(), p2.rd().data());
+```
+"
+)]
+pub struct PBufTxn<'a, T: Copy + Default + 'static = u8> {
+    wr: PBufWr<'a, T>,
+    len: usize,
+    state: PBufState,
+    done: bool,
+    #[cfg(feature = "waker")]
+    outer: bool,
+}
+
+impl<'a, T: Copy + Default + 'static> PBufTxn<'a, T> {
+    /// Get a producer reference to write to the buffer within this
+    /// transaction
+    #[inline(always)]
+    pub fn wr(&mut self) -> PBufWr<'_, T> {
+        self.wr.reborrow()
+    }
+
+    /// Get mutable access to the data written so far within this
+    /// transaction.  This data is not visible to the consumer until
+    /// the transaction is committed, so it may still be modified.
+    /// For example, a length-prefixed encoder can append a
+    /// placeholder header, write the body, and then fill in the
+    /// header once the body length is known:
+    ///
+    #[cfg_attr(
+        any(feature = "std", feature = "alloc"),
+        doc = "
+```
+# use pipebuf::PipeBuf;
+let mut p = PipeBuf::new();
+let mut wr = p.wr();
+let mut txn = wr.transaction();
+txn.wr().append(&[0; 2]);
+txn.wr().append(b\"body\");
+let len = txn.written_mut().len() as u16 - 2;
+txn.written_mut()[..2].copy_from_slice(&len.to_be_bytes());
+txn.commit();
+assert_eq!(p.rd().data(), b\"\\0\\x04body\");
+```
+"
+    )]
+    #[inline]
+    pub fn written_mut(&mut self) -> &mut [T] {
+        let pb = &mut *self.wr.pb;
+        &mut pb.data[pb.rd + self.len..pb.wr]
+    }
+
+    /// Commit the transaction, keeping all the data written.  With
+    /// the "waker" feature, this is when the consumer's waker is
+    /// woken, unless this transaction is nested within another.
+    #[inline]
+    pub fn commit(mut self) {
+        self.done = true;
+    }
+
+    /// Abandon the transaction, discarding all the data written.
+    /// This is the same as dropping the transaction.
+    #[inline]
+    pub fn rollback(self) {}
+}
+
+impl<'a, T: Copy + Default + 'static> Drop for PBufTxn<'a, T> {
+    fn drop(&mut self) {
+        if !self.done {
+            // The consumer cannot have run during the transaction, so
+            // the data from before the transaction is still at the
+            // start of the unread region, even if compacted
+            let pb = &mut *self.wr.pb;
+            let wr = pb.rd + self.len;
+            if pb.wiping() {
+                pb.wipe_range(wr, pb.wr);
+            }
+            pb.wr = wr;
+            pb.state = self.state;
+        }
+        #[cfg(feature = "waker")]
+        if !self.outer {
+            let pb = &mut *self.wr.pb;
+            pb.set_in_txn(false);
+            if pb.wr - pb.rd != self.len || pb.state != self.state {
+                pb.wake_rd();
+            }
+        }
+    }
+}
+
 // Panic code is pulled out into non-inlined functions to reduce
 // overhead in inlined code
 #[inline(never)]
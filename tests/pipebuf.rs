@@ -6,6 +6,9 @@
 //! usually producer code, consumer code and glue code would be
 //! separate.
 
+#![allow(clippy::bool_assert_comparison)]
+#![allow(clippy::non_minimal_cfg)]
+
 use pipebuf::PBufState;
 
 #[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
@@ -98,6 +101,15 @@ fn states() {
     p.set_push(false);
     assert_eq!(PBufState::Open, p.state());
 
+    // Override state
+    p.force_state(PBufState::Aborting);
+    assert_eq!(true, p.rd().is_aborted());
+    p.force_state(PBufState::Open);
+    assert_eq!(false, p.rd().is_eof());
+    p.force_state(PBufState::Closed);
+    assert_eq!(true, p.is_done());
+    p.force_state(PBufState::Open);
+
     // Add data
     p.wr().append(b"0");
     assert_eq!(false, p.rd().is_empty());
@@ -157,6 +169,11 @@ fn states() {
     assert_eq!(false, p.rd().is_done());
     assert_eq!(PBufState::Open, p.rd().state());
 
+    let rd = p.rd();
+    let [d0, d1] = rd.data_vectored();
+    assert_eq!(b"12", d0);
+    assert_eq!(true, d1.is_empty());
+
     // Add normal EOF
     p.wr().close();
     assert_eq!(false, p.rd().is_empty());
@@ -367,6 +384,21 @@ fn with_capacity() {
     p.wr().append(b"0123456789");
     p.wr().append(b"ABCDEFGHIJ");
     assert_eq!(b"0123456789ABCDEFGHIJ", p.rd().data());
+
+    assert!(p.capacity() >= 20);
+    p.rd().consume(15);
+    p.shrink_to(8);
+    assert_eq!(8, p.capacity());
+    assert_eq!(b"FGHIJ", p.rd().data());
+    p.rd().consume(5);
+    p.shrink_to(0);
+    assert_eq!(0, p.capacity());
+    p.wr().append(b"01");
+    assert_eq!(b"01", p.rd().data());
+
+    let mut p = PipeBuf::<u8>::with_fixed_capacity(10);
+    p.shrink_to(0);
+    assert_eq!(10, p.capacity());
 }
 
 #[cfg(any(feature = "std", feature = "alloc"))]
@@ -434,6 +466,15 @@ fn compact_buffer() {
     p.rd().consume(5);
     p.wr().append(b"UVWXYZ");
     assert_eq!(b"PQRSTUVWXYZ", p.rd().data());
+
+    // Explicit compaction
+    p.rd().consume(8);
+    p.compact();
+    assert_eq!(b"XYZ", p.rd().data());
+    p.compact();
+    assert_eq!(b"XYZ", p.rd().data());
+    p.wr().append(b"0123456789");
+    assert_eq!(b"XYZ0123456789", p.rd().data());
 }
 
 #[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
@@ -541,6 +582,75 @@ fn forward() {
     assert!(q.rd().is_aborted());
 }
 
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn forward_swap() {
+    let mut p = PipeBuf::<u8>::with_capacity(1000);
+    let mut q = PipeBuf::<u8>::new();
+    p.wr().append(b"0123456789");
+    p.rd().consume(2);
+    p.wr().push();
+    p.rd().forward(q.wr());
+    assert_eq!(b"23456789", q.rd().data());
+    assert_eq!(true, q.is_push());
+    assert_eq!(true, p.rd().is_empty());
+    assert_eq!(1000, q.capacity());
+    assert_eq!(0, p.capacity());
+
+    // Destination not empty, so data is copied
+    p.wr().append(b"AB");
+    p.wr().close();
+    p.rd().forward(q.wr());
+    assert_eq!(b"23456789AB", q.rd().data());
+    assert_eq!(PBufState::Closing, q.state());
+    assert_eq!(true, p.is_done());
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn forward_swap_growth_hook() {
+    // A destination with a growth hook gets a copy, and the hook sees
+    // the growth
+    let mut p = PipeBuf::<u8>::with_capacity(1000);
+    let mut q = PipeBuf::<u8>::new();
+    q.set_growth_hook(|_, new| new <= 8);
+    p.wr().append(b"0123");
+    p.rd().forward(q.wr());
+    assert_eq!(b"0123", q.rd().data());
+    assert_eq!(1000, p.capacity());
+    assert_eq!(8, q.capacity());
+
+    // A source with a growth hook keeps its memory, so the hook's
+    // accounting stays correct
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    let total = Arc::new(AtomicUsize::new(0));
+    let total2 = total.clone();
+    let mut p = PipeBuf::<u8>::new();
+    p.set_growth_hook(move |old, new| {
+        total2.fetch_add(new - old, Ordering::SeqCst);
+        true
+    });
+    for _ in 0..3 {
+        let mut q = PipeBuf::<u8>::new();
+        p.wr().append(&[0; 1000]);
+        p.rd().forward(q.wr());
+        assert_eq!(1000, q.rd().len());
+    }
+    assert_eq!(p.capacity(), total.load(Ordering::SeqCst));
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+#[should_panic(expected = "growth vetoed")]
+fn growth_hook_veto_panic() {
+    let mut p = PipeBuf::<u8>::new();
+    p.set_growth_hook(|_, _| false);
+    p.wr().space(1);
+}
+
 #[cfg(any(feature = "std"))]
 #[test]
 fn read_trait() {
@@ -834,3 +944,1194 @@ fn pipebufpair_var() {
     assert!(ut != p.upper().tripwire());
     assert!(lt != p.lower().tripwire());
 }
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn pool() {
+    let mut pool = pipebuf::PipeBufPool::<u8>::with_fixed_capacity(10);
+    pool.set_zero(true);
+    assert_eq!(0, pool.in_use());
+    assert_eq!(0, pool.available());
+
+    let mut p1 = pool.get();
+    let mut p2 = pool.get();
+    assert_eq!(2, pool.in_use());
+    assert_eq!(2, pool.high_water());
+    assert_eq!(Some(10), p1.wr().free_space());
+
+    p1.wr().append(b"0123");
+    p1.wr().close();
+    pool.put(p1);
+    assert_eq!(1, pool.in_use());
+    assert_eq!(1, pool.available());
+
+    let mut p3 = pool.get();
+    assert_eq!(0, pool.available());
+    assert_eq!(PBufState::Open, p3.state());
+    assert_eq!(true, p3.rd().is_empty());
+    assert_eq!(&[0; 4], p3.wr().space(4));
+
+    p2.wr().append(b"x");
+    pool.put(p2);
+    pool.put(p3);
+    assert_eq!(0, pool.in_use());
+    assert_eq!(2, pool.high_water());
+    assert_eq!(2, pool.available());
+    pool.clear();
+    assert_eq!(0, pool.available());
+
+    let mut pool = pipebuf::PipeBufPool::<u8>::with_capacity(4);
+    let mut p = pool.get();
+    assert_eq!(None, p.wr().free_space());
+    p.wr().append(b"0123456789");
+    pool.put(p);
+    assert_eq!(1, pool.available());
+
+    // Grown buffers are shrunk, and mismatched buffers are dropped
+    let p = pool.get();
+    assert_eq!(4, p.capacity());
+    pool.put(PipeBuf::with_fixed_capacity(4));
+    assert_eq!(0, pool.available());
+    let mut p2 = PipeBuf::with_capacity(4);
+    p2.wr().append(&[0; 100]);
+    pool.put(p2);
+    assert_eq!(4, pool.get().capacity());
+    pool.put(p);
+    let mut fixed = pipebuf::PipeBufPool::<u8>::with_fixed_capacity(16);
+    fixed.put(PipeBuf::with_fixed_capacity(8));
+    fixed.put(PipeBuf::new());
+    assert_eq!(0, fixed.available());
+    fixed.put(PipeBuf::with_fixed_capacity(16));
+    assert_eq!(1, fixed.available());
+
+    // Settings are not carried over to the next user
+    let mut p = pool.get();
+    p.set_writable(false);
+    p.set_relaxed(true);
+    p.set_history(4);
+    p.set_growth_hook(|_, _| false);
+    pool.put(p);
+    let mut p = pool.get();
+    assert_eq!(true, p.is_writable());
+    p.wr().append(b"0123456789ABCDEFGHIJ");
+    p.rd().consume(4);
+    assert_eq!(b"", p.rd().history());
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn split_off() {
+    let mut p = PipeBuf::<u8>::new();
+    p.wr().append(b"GET / HTTP/1.1\r\n\r\nWSDATA");
+    p.rd().consume(18);
+    p.wr().push();
+    let mut p2 = p.split_off();
+    assert_eq!(true, p.rd().is_empty());
+    assert_eq!(true, p.is_done());
+    assert_eq!(PBufState::Closed, p.state());
+    assert_eq!(b"WSDATA", p2.rd().data());
+    assert_eq!(PBufState::Push, p2.state());
+    p2.wr().append(b"+");
+    assert_eq!(b"WSDATA+", p2.rd().data());
+
+    p2.wr().abort();
+    let p3 = p2.split_off();
+    assert_eq!(PBufState::Aborted, p2.state());
+    assert_eq!(PBufState::Aborting, p3.state());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn append_pipebuf() {
+    let p = fixed_capacity_pipebufpair!(20);
+    let mut out = p.down;
+    let mut req = p.up;
+
+    req.wr().append(b"abc");
+    req.wr().close();
+    out.wr().append_pipebuf(&mut req, false);
+    assert_eq!(b"abc", out.rd().data());
+    assert_eq!(true, req.rd().is_empty());
+    assert_eq!(PBufState::Closing, req.state());
+    assert_eq!(PBufState::Open, out.state());
+    out.wr().append_pipebuf(&mut req, false);
+    assert_eq!(b"abc", out.rd().data());
+
+    req.reset();
+    req.wr().append(b"def");
+    req.wr().close();
+    out.wr().append_pipebuf(&mut req, true);
+    assert_eq!(b"abcdef", out.rd().data());
+    assert_eq!(true, req.is_done());
+    assert_eq!(PBufState::Closing, out.state());
+}
+
+#[cfg(any(feature = "std"))]
+#[test]
+fn debug_dump() {
+    let mut p = PipeBuf::with_fixed_capacity(64);
+    p.wr().append(b"XXGET / HTTP/1.1\r\nHost: example.com\r\n");
+    p.rd().consume(2);
+    assert_eq!(
+        "PipeBuf { state: Open, len: 35, capacity: 64 }",
+        format!("{:?}", p)
+    );
+    assert_eq!(
+        "0000  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31 0d 0a  |GET / HTTP/1.1..|\n\
+         0010  48 6f 73 74 3a 20 65 78  61 6d 70 6c 65 2e 63 6f  |Host: example.co|\n\
+         0020  6d 0d 0a                                          |m..|\n",
+        format!("{}", p.dump(100))
+    );
+    assert_eq!(
+        "0000  47 45 54 20 2f                                    |GET /|\n\
+         ... 30 more bytes\n",
+        format!("{}", p.dump(5))
+    );
+
+    let mut p = PipeBuf::<u8>::with_capacity(16).debug_preview(4);
+    p.wr().append(b"\r\n");
+    assert_eq!(
+        "PipeBuf { state: Open, len: 2, capacity: 16, preview: b\"\\r\\n\" }",
+        format!("{:?}", p)
+    );
+    p.wr().append(b"abcd");
+    p.wr().close();
+    assert_eq!(
+        "PipeBuf { state: Closing, len: 6, capacity: 16, preview: b\"\\r\\nab\"... }",
+        format!("{:?}", p)
+    );
+
+    let mut p = PipeBuf::<char>::new().debug_preview(2);
+    p.wr().append(&['a', 'b', 'c']);
+    assert!(format!("{:?}", p).ends_with("preview: ['a', 'b']... }"));
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn drain_iter() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.wr().append(b"  abc def");
+    let mut rd = p.rd();
+    let mut it = rd.drain_iter();
+    assert_eq!(9, it.len());
+    assert_eq!(Some(b' '), it.next());
+    assert_eq!(Some(b' '), it.next());
+    let word: Vec<u8> = it.by_ref().take_while(|b| *b != b' ').collect();
+    assert_eq!(b"abc", &word[..]);
+    assert_eq!(3, it.len());
+    assert_eq!(b"def", rd.data());
+    assert_eq!(3, rd.drain_iter().count());
+    assert_eq!(true, rd.is_empty());
+    assert_eq!(None, rd.drain_iter().next());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn consume_while() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.wr().append(b"  \t123x");
+    assert_eq!(3, p.rd().consume_while(|b| b.is_ascii_whitespace()));
+    assert_eq!(0, p.rd().consume_while(|b| b.is_ascii_whitespace()));
+    assert_eq!(b"123x", p.rd().data());
+    assert_eq!(3, p.rd().consume_while(|b| b.is_ascii_digit()));
+    assert_eq!(1, p.rd().consume_while(|_| true));
+    assert_eq!(0, p.rd().consume_while(|_| true));
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn reopen() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    assert_eq!(false, p.reopen());
+    p.wr().append(b"req1req2");
+    p.wr().close();
+    assert_eq!(false, p.reopen());
+    assert_eq!(true, p.rd().consume_eof());
+    p.rd().consume(4);
+    // Leftover data from the previous stream prevents reopening
+    assert_eq!(false, p.reopen());
+    assert_eq!(PBufState::Closed, p.state());
+    p.rd().consume(4);
+    assert_eq!(true, p.reopen());
+    assert_eq!(PBufState::Open, p.state());
+    p.wr().append(b"req3");
+    assert_eq!(b"req3", p.rd().data());
+
+    p.wr().abort();
+    assert_eq!(true, p.rd().consume_eof());
+    assert_eq!(false, p.reopen());
+    p.rd().discard_all();
+    assert_eq!(true, p.reopen());
+    assert_eq!(false, p.rd().is_eof());
+    assert_eq!(true, p.rd().is_empty());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn close_with_final() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.wr().append(b"0123456");
+    assert_eq!(false, p.wr().close_with_final(b"END!"));
+    assert_eq!(PBufState::Open, p.state());
+    assert_eq!(b"0123456", p.rd().data());
+    p.rd().consume(4);
+    assert_eq!(true, p.wr().close_with_final(b"END!"));
+    assert_eq!(PBufState::Closing, p.state());
+    assert_eq!(b"456END!", p.rd().data());
+    assert_eq!(false, p.wr().close_with_final(b""));
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn transaction() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.wr().append(b"0123");
+    p.rd().consume(2);
+
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    txn.wr().append(b"AB");
+    txn.wr().push();
+    assert_eq!(true, txn.wr().try_space(10).is_none());
+    drop(txn);
+    assert_eq!(b"23", p.rd().data());
+    assert_eq!(PBufState::Open, p.state());
+
+    // Rollback after compaction
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    txn.wr().append(b"ABCDEF");
+    txn.wr().append(b"GH");
+    txn.wr().close();
+    txn.rollback();
+    assert_eq!(b"23", p.rd().data());
+    assert_eq!(PBufState::Open, p.state());
+
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    txn.wr().append(b"AB");
+    txn.wr().append(b"CD");
+    txn.wr().close();
+    txn.commit();
+    assert_eq!(b"23ABCD", p.rd().data());
+    assert_eq!(PBufState::Closing, p.state());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn transaction_multi() {
+    let mut p = fixed_capacity_pipebufpair!(8);
+
+    // Write a frame to both outputs, or to neither
+    let frame = |p: &mut PipeBufPair, data: &[u8]| {
+        let mut wr1 = p.down.wr();
+        let mut wr2 = p.up.wr();
+        let mut t1 = wr1.transaction();
+        let mut t2 = wr2.transaction();
+        for mut wr in [t1.wr(), t2.wr()] {
+            if wr.free_space().unwrap() < data.len() + 1 {
+                return false;
+            }
+            wr.append(&[data.len() as u8]);
+            wr.append(data);
+        }
+        t1.commit();
+        t2.commit();
+        true
+    };
+
+    assert_eq!(true, frame(&mut p, b"abc"));
+    p.up.rd().consume(4);
+    assert_eq!(false, frame(&mut p, b"defg"));
+    assert_eq!(b"\x03abc", p.down.rd().data());
+    assert_eq!(true, p.up.rd().is_empty());
+    p.down.rd().consume(4);
+    assert_eq!(true, frame(&mut p, b"defg"));
+    assert_eq!(b"\x04defg", p.down.rd().data());
+    assert_eq!(b"\x04defg", p.up.rd().data());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn history() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    assert_eq!(b"", p.rd().history());
+    p.wr().append(b"0123");
+    p.rd().consume(2);
+    assert_eq!(b"", p.rd().history());
+
+    p.reset();
+    p.set_history(3);
+    assert_eq!(Some(10), p.wr().free_space());
+    p.wr().append(b"0123");
+    p.rd().consume(2);
+    assert_eq!(b"01", p.rd().history());
+    p.rd().consume(2);
+    assert_eq!(b"123", p.rd().history());
+    assert_eq!(Some(10), p.wr().free_space());
+
+    // Compaction keeps the history, and empty buffer is not reset
+    p.wr().append(b"456789");
+    assert_eq!(b"123", p.rd().history());
+    assert_eq!(b"456789", p.rd().data());
+    assert_eq!(Some(4), p.wr().free_space());
+    p.rd().consume(5);
+    assert_eq!(b"678", p.rd().history());
+    p.wr().append(b"ABCDEF");
+    assert_eq!(b"678", p.rd().history());
+    assert_eq!(b"9ABCDEF", p.rd().data());
+    assert_eq!(Some(3), p.wr().free_space());
+    p.compact();
+    assert_eq!(b"678", p.rd().history());
+
+    p.rd().rewind(2);
+    assert_eq!(b"6", p.rd().history());
+    assert_eq!(b"789ABCDEF", p.rd().data());
+    p.rd().consume(4);
+    assert_eq!(b"89A", p.rd().history());
+
+    // History gives way to the producer rather than blocking it
+    let mut p = fixed_capacity_pipebuf!(8);
+    p.set_history(8);
+    p.wr().append(b"01234567");
+    p.rd().consume(8);
+    assert_eq!(b"01234567", p.rd().history());
+    assert_eq!(Some(8), p.wr().free_space());
+    assert_eq!(true, p.wr().try_space(3).is_some());
+    assert_eq!(b"34567", p.rd().history());
+    p.wr().append(b"abcdefgh");
+    assert_eq!(b"", p.rd().history());
+    assert_eq!(b"abcdefgh", p.rd().data());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+#[should_panic]
+fn rewind_overflow() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.set_history(2);
+    p.wr().append(b"0123");
+    p.rd().consume(3);
+    p.rd().rewind(3);
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn discard() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.wr().append(b"0123456789");
+    p.rd().discard_tail(3);
+    assert_eq!(b"0123456", p.rd().data());
+    p.rd().consume(2);
+    p.rd().discard_tail(0);
+    assert_eq!(b"23456", p.rd().data());
+    p.wr().append(b"ABC");
+    assert_eq!(b"23456ABC", p.rd().data());
+    p.rd().discard_all();
+    assert_eq!(true, p.rd().is_empty());
+    p.rd().discard_all();
+    assert_eq!(true, p.rd().is_empty());
+
+    // Discarded data doesn't enter the history
+    p.set_history(4);
+    p.wr().append(b"abcdef");
+    p.rd().consume(2);
+    assert_eq!(b"ab", p.rd().history());
+    p.rd().discard_all();
+    assert_eq!(true, p.rd().is_empty());
+    assert_eq!(b"", p.rd().history());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+#[should_panic]
+fn discard_tail_overflow() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.wr().append(b"0123");
+    p.rd().discard_tail(5);
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn wipe_on_consume() {
+    let mut p = fixed_capacity_pipebuf!(10);
+    p.set_wipe_on_consume(true);
+    p.wr().append(b"0123456789");
+    p.rd().consume(3);
+    p.rd().discard_tail(2);
+    assert_eq!(b"34567", p.rd().data());
+    // Consumed and discarded data is zeroed, and compaction leaves no
+    // copies behind
+    p.compact();
+    assert_eq!(&[0; 5], p.wr().space(5));
+    assert_eq!(b"34567", p.rd().data());
+    p.rd().consume(5);
+    p.rd().discard_all();
+    assert_eq!(&[0; 10], p.wr().space(10));
+
+    // Data is wiped when it falls out of the history window
+    p.set_history(2);
+    p.wr().append(b"abcde");
+    p.rd().consume(4);
+    assert_eq!(b"cd", p.rd().history());
+    assert_eq!(1, p.rd().consume_while(|_| true));
+    assert_eq!(b"de", p.rd().history());
+    // Raising the window does not bring back wiped data
+    p.set_history(5);
+    assert_eq!(b"de", p.rd().history());
+    p.set_history(0);
+    p.compact();
+    assert_eq!(&[0; 10], p.wr().space(10));
+
+    // Rolled-back transaction data is wiped
+    p.wr().append(b"ab");
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    txn.wr().append(b"cd");
+    drop(txn);
+    assert_eq!(2, p.rd().drain_iter().count());
+    assert_eq!(&[0; 10], p.wr().space(10));
+
+    // Unread data is wiped on reset
+    p.wr().append(b"abc");
+    p.reset();
+    assert_eq!(&[0; 10], p.wr().space(10));
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn wipe_on_consume_realloc() {
+    let mut p = PipeBuf::<u8>::with_capacity(4);
+    p.set_wipe_on_consume(true);
+    p.wr().append(b"0123");
+    p.wr().append(b"4567");
+    assert_eq!(b"01234567", p.rd().data());
+    p.rd().consume(6);
+    p.shrink_to(0);
+    assert_eq!(2, p.capacity());
+    assert_eq!(b"67", p.rd().data());
+    let mut q = p.split_off();
+    assert_eq!(b"67", q.rd().data());
+    // The new buffer wipes too
+    q.rd().consume(2);
+    assert_eq!(&[0; 2], q.wr().space(2));
+    p.reopen();
+    assert_eq!(&[0; 2], p.wr().space(2));
+}
+
+#[cfg(any(feature = "std"))]
+#[test]
+fn try_writer() {
+    use std::io::{ErrorKind, Write};
+    let mut p = PipeBuf::with_fixed_capacity(10);
+
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    let res = write!(txn.wr().try_writer(), "{{\"value\":{}}}", 12345);
+    assert_eq!(std::io::ErrorKind::WriteZero, res.unwrap_err().kind());
+    drop(txn);
+    assert_eq!(true, p.rd().is_empty());
+
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    write!(txn.wr().try_writer(), "{{\"v\":{}}}", 123).unwrap();
+    txn.wr().try_writer().flush().unwrap();
+    txn.commit();
+    assert_eq!(b"{\"v\":123}", p.rd().data());
+    assert_eq!(true, p.is_push());
+    assert_eq!(0, p.wr().try_writer().write(b"").unwrap());
+
+    let mut p = PipeBuf::new();
+    p.wr().try_writer().write_all(b"0123456789").unwrap();
+    assert_eq!(b"0123456789", p.rd().data());
+
+    // Vetoed growth is reported as an error rather than panicking
+    p.set_growth_hook(|_, _| false);
+    let res = p.wr().try_writer().write(b"ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+    assert!(matches!(res, Err(e) if e.kind() == ErrorKind::WriteZero));
+    assert_eq!(b"0123456789", p.rd().data());
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn const_new() {
+    const P: PipeBuf<u8> = PipeBuf::new();
+    const PP: PipeBufPair<u8> = PipeBufPair::new();
+    let mut p = P;
+    p.wr().append(b"abc");
+    assert_eq!(b"abc", p.rd().data());
+    let mut pp = PP;
+    pp.left().wr.append(b"xyz");
+    assert_eq!(b"xyz", pp.right().rd.data());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn const_new_static_mutex() {
+    static P: std::sync::Mutex<PipeBuf<u8>> = std::sync::Mutex::new(PipeBuf::new());
+    P.lock().unwrap().wr().append(b"abc");
+    assert_eq!(b"abc", P.lock().unwrap().rd().data());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn set_writable() {
+    let mut p = fixed_capacity_pipebuf!(8);
+    assert_eq!(true, p.is_writable());
+    assert_eq!(Some(8), p.wr().free_space());
+    p.set_writable(false);
+    assert_eq!(false, p.is_writable());
+    assert_eq!(Some(0), p.wr().free_space());
+    assert_eq!(None, p.wr().try_space(1));
+    assert_eq!(Some(0), p.wr().try_space(0).map(|s| s.len()));
+    p.reset();
+    assert_eq!(false, p.is_writable());
+    p.set_writable(true);
+    assert_eq!(Some(4), p.wr().try_space(4).map(|s| s.len()));
+
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    {
+        let mut p = PipeBuf::<u8>::new();
+        assert_eq!(None, p.wr().free_space());
+        p.set_writable(false);
+        assert_eq!(Some(0), p.wr().free_space());
+        assert_eq!(None, p.wr().try_space(1));
+    }
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn readiness() {
+    let mut p = fixed_capacity_pipebuf!(8);
+    let r = p.readiness();
+    assert_eq!(true, r.is_empty());
+    assert_eq!(Some(8), r.free_space());
+    assert_eq!(true, r.has_space(8));
+    assert_eq!(false, r.has_space(9));
+    assert_eq!(PBufState::Open, r.state());
+
+    p.wr().append(b"abc");
+    p.wr().push();
+    let r = p.readiness();
+    assert_eq!(3, r.len());
+    assert_eq!(true, r.has_data(3));
+    assert_eq!(false, r.has_data(4));
+    assert_eq!(true, r.is_push());
+    assert_eq!(false, r.is_eof());
+    assert_eq!(r, p.readiness());
+
+    p.wr().close();
+    let r = p.readiness();
+    assert_eq!(true, r.has_pending_eof());
+    assert_eq!(true, r.is_eof());
+    assert_eq!(false, r.is_done());
+
+    p.rd().consume(3);
+    assert_eq!(true, p.rd().consume_eof());
+    let r = p.readiness();
+    assert_eq!(false, r.has_pending_eof());
+    assert_eq!(true, r.is_done());
+
+    p.reset();
+    p.set_writable(false);
+    assert_eq!(false, p.readiness().has_space(1));
+
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    assert_eq!(true, PipeBuf::<u8>::new().readiness().has_space(usize::MAX));
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn tripset() {
+    use pipebuf::PBufTripSet;
+    let mut p = [PipeBuf::<u8>::new(), PipeBuf::new(), PipeBuf::new()];
+    let mut set = PBufTripSet::new();
+    let trips = |p: &[PipeBuf<u8>; 3]| [p[0].tripwire(), p[1].tripwire(), p[2].tripwire()];
+    let update_all = |set: &mut PBufTripSet, p: &[PipeBuf<u8>; 3]| {
+        let mut changed = Vec::new();
+        set.update_all(trips(p), &mut changed);
+        changed
+    };
+
+    assert_eq!(vec![0, 1, 2], update_all(&mut set, &p));
+    assert_eq!(Vec::<usize>::new(), update_all(&mut set, &p));
+
+    p[0].wr().append(b"abc");
+    p[2].wr().close();
+    assert_eq!(true, set.is_tripped(2, p[2].tripwire()));
+    assert_eq!(false, set.is_tripped(1, p[1].tripwire()));
+    assert_eq!(vec![0, 2], update_all(&mut set, &p));
+
+    p[0].rd().consume(1);
+    assert_eq!(true, set.update(0, p[0].rd().tripwire()));
+    assert_eq!(false, set.update(0, p[0].tripwire()));
+    assert_eq!(true, set.update(5, p[1].tripwire()));
+    assert_eq!(true, set.is_tripped(4, p[1].tripwire()));
+
+    set.clear();
+    assert_eq!(vec![0, 1, 2], update_all(&mut set, &p));
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn tripwire_pair() {
+    use pipebuf::tripwire;
+    let mut pp = fixed_capacity_pipebufpair!(16);
+    let mut p = fixed_capacity_pipebuf!(16);
+    let t0 = tripwire!(pp, p);
+    assert_eq!(true, t0 == tripwire!(pp, p));
+    pp.upper().wr.append(b"x");
+    assert_eq!(false, t0 == tripwire!(pp, p));
+
+    let t1 = pp.tripwire();
+    assert_eq!(false, pp.is_tripped(t1));
+    let rdwr = pp.lower();
+    let t2 = rdwr.tripwire();
+    assert_eq!(false, rdwr.is_tripped(t2));
+    let mut rdwr = pp.lower();
+    rdwr.rd.consume(1);
+    assert_eq!(true, rdwr.is_tripped(t2));
+    pp.lower().wr.close();
+    assert_eq!(true, pp.is_tripped(t1));
+    let _ = p.wr();
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn growth_hook() {
+    use std::sync::{Arc, Mutex};
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log2 = log.clone();
+    let mut p = PipeBuf::<u8>::with_capacity(4);
+    p.set_growth_hook(move |old, new| {
+        log2.lock().unwrap().push((old, new));
+        new <= 32
+    });
+    p.wr().append(b"0123");
+    assert_eq!(0, log.lock().unwrap().len());
+    p.wr().append(b"4567");
+    assert_eq!(vec![(4, 8)], *log.lock().unwrap());
+    assert_eq!(8, p.capacity());
+    assert_eq!(true, p.wr().try_space(12).is_some());
+    assert_eq!(vec![(4, 8), (8, 24)], *log.lock().unwrap());
+    assert_eq!(24, p.capacity());
+    assert_eq!(true, p.wr().try_space(40).is_none());
+    assert_eq!(24, p.capacity());
+    assert_eq!(b"01234567", p.rd().data());
+
+    p.clear_growth_hook();
+    assert_eq!(true, p.wr().try_space(40).is_some());
+    assert_eq!(3, log.lock().unwrap().len());
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn pair_mixed_types() {
+    use pipebuf::PBufRdWr;
+    let mut pair: PipeBufPair<u8, char> = PipeBufPair {
+        down: PipeBuf::new(),
+        up: PipeBuf::new(),
+    };
+
+    // Decodes ASCII bytes from above and passes chars back up
+    fn decode(mut rdwr: PBufRdWr<u8, char>) -> bool {
+        let len = rdwr.rd.len();
+        for &b in rdwr.rd.data() {
+            rdwr.wr.append(&[b as char]);
+        }
+        rdwr.rd.consume(len);
+        len > 0
+    }
+
+    pair.upper().wr.append(b"hi");
+    let before = pair.tripwire();
+    assert_eq!(true, decode(pair.lower()));
+    assert_eq!(true, pair.is_tripped(before));
+    assert_eq!(['h', 'i'], pair.upper().rd.data());
+    assert_eq!(true, pair.lower().rd.is_empty());
+    pair.reset();
+    assert_eq!(true, pair.upper().rd.is_empty());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn pbufref() {
+    use pipebuf::PBufRef;
+    fn summary(pb: &dyn PBufRef) -> (PBufState, usize, Option<usize>, bool, bool) {
+        (
+            pb.state(),
+            pb.len(),
+            pb.free_space(),
+            pb.is_eof(),
+            pb.is_done(),
+        )
+    }
+    let mut p = fixed_capacity_pipebuf!(8);
+    p.wr().append(b"abc");
+    p.wr().close();
+    let expect = (PBufState::Closing, 3, Some(5), true, false);
+    assert_eq!(expect, summary(&p));
+    assert_eq!(expect, summary(&p.rd()));
+    assert_eq!(expect, summary(&p.wr()));
+
+    let trip = PBufRef::tripwire(&p);
+    assert_eq!(false, PBufRef::is_tripped(&p.rd(), trip));
+    assert_eq!(false, PBufRef::is_empty(&p.wr()));
+    p.rd().consume(3);
+    assert_eq!(true, PBufRef::is_tripped(&p.wr(), trip));
+    assert_eq!(true, PBufRef::is_empty(&p));
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn relaxed() {
+    let mut p = fixed_capacity_pipebuf!(8);
+    p.set_relaxed(true);
+    p.wr().append(b"abc");
+    assert_eq!(false, p.has_overflowed());
+
+    p.rd().consume(5);
+    assert_eq!(true, p.has_overflowed());
+    assert_eq!(true, p.rd().is_empty());
+    p.clear_overflow();
+    assert_eq!(false, p.has_overflowed());
+
+    p.wr().append(b"abcd");
+    p.rd().discard_tail(6);
+    assert_eq!(true, p.has_overflowed());
+    assert_eq!(true, p.rd().is_empty());
+    p.reset();
+    assert_eq!(false, p.has_overflowed());
+
+    p.wr().space(2);
+    p.wr().commit(20);
+    assert_eq!(true, p.has_overflowed());
+    assert_eq!(8, p.rd().len());
+    p.clear_overflow();
+
+    p.wr().close();
+    p.wr().commit(1);
+    assert_eq!(true, p.has_overflowed());
+    assert_eq!(8, p.rd().len());
+
+    p.reset();
+    p.set_history(4);
+    p.wr().append(b"abcd");
+    p.rd().consume(2);
+    p.rd().rewind(3);
+    assert_eq!(true, p.has_overflowed());
+    assert_eq!(b"abcd", p.rd().data());
+
+    // Huge lengths are clamped without arithmetic overflow
+    p.reset();
+    p.set_history(0);
+    p.wr().append(b"ab");
+    p.rd().consume(usize::MAX);
+    assert_eq!(true, p.rd().is_empty());
+    p.wr().space(2);
+    p.wr().commit(usize::MAX);
+    assert_eq!(8, p.rd().len());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn space_io_slices() {
+    use std::io::Read;
+    let mut src: &[u8] = b"0123456789";
+    let mut p = PipeBuf::new();
+    let mut wr = p.wr();
+    let mut slices = wr.space_io_slices(4);
+    assert_eq!(4, slices[0].len());
+    assert_eq!(0, slices[1].len());
+    let len = src.read_vectored(&mut slices).unwrap();
+    assert_eq!(4, len);
+    wr.commit(len);
+    assert_eq!(b"0123", p.rd().data());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn fill_drain() {
+    use pipebuf::{PBufDrainer, PBufFiller};
+
+    // Source giving at most 3 bytes per call
+    struct Src(&'static [u8]);
+    impl PBufFiller for Src {
+        type Error = ();
+        fn fill(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+            let len = buf.len().min(self.0.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    // Sink taking at most `room` bytes, and failing when `room` is 99
+    struct Dst {
+        out: [u8; 16],
+        len: usize,
+        room: usize,
+        flushed: bool,
+    }
+    impl PBufDrainer for Dst {
+        type Error = &'static str;
+        fn drain(&mut self, data: &[u8]) -> Result<usize, &'static str> {
+            if self.room == 99 {
+                return Err("failed");
+            }
+            let len = data.len().min(self.room);
+            self.out[self.len..self.len + len].copy_from_slice(&data[..len]);
+            self.len += len;
+            self.room -= len;
+            Ok(len)
+        }
+        fn flush(&mut self) -> Result<(), &'static str> {
+            self.flushed = true;
+            Ok(())
+        }
+    }
+
+    let mut p = fixed_capacity_pipebuf!(16);
+    let mut src = Src(b"0123456789");
+    assert_eq!(Ok(7), p.wr().fill_from(&mut src, 7));
+    assert_eq!(Ok(3), p.wr().fill_from(&mut src, 7));
+    assert_eq!(Ok(0), p.wr().fill_from(&mut src, 6));
+    assert_eq!(b"0123456789", p.rd().data());
+    p.wr().push();
+
+    let mut dst = Dst {
+        out: [0; 16],
+        len: 0,
+        room: 4,
+        flushed: false,
+    };
+    assert_eq!(Ok(4), p.rd().drain_to(&mut dst, false));
+    assert_eq!(false, dst.flushed);
+    dst.room = 10;
+    assert_eq!(Ok(6), p.rd().drain_to(&mut dst, false));
+    assert_eq!(true, dst.flushed);
+    assert_eq!(false, p.is_push());
+    assert_eq!(b"0123456789", &dst.out[..dst.len]);
+
+    p.wr().append(b"x");
+    dst.room = 99;
+    assert_eq!(Err("failed"), p.rd().drain_to(&mut dst, true));
+
+    p.wr().close();
+    assert_eq!(Ok(0), p.wr().fill_from(&mut Src(b"abc"), 3));
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn transaction_written_mut() {
+    let mut p = fixed_capacity_pipebuf!(16);
+    p.wr().append(b"xyz");
+    p.rd().consume(2);
+
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    assert_eq!(0, txn.written_mut().len());
+    txn.wr().append(b"#");
+    txn.wr().append(b"0123456789ab");
+    let len = txn.written_mut().len() - 1;
+    txn.written_mut()[0] = b'a' + len as u8;
+    txn.commit();
+    assert_eq!(b"zm0123456789ab", p.rd().data());
+
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    txn.wr().append(b"!");
+    txn.written_mut()[0] = b'?';
+    drop(txn);
+    assert_eq!(b"zm0123456789ab", p.rd().data());
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn extend() {
+    let mut p = fixed_capacity_pipebuf!(16);
+    p.extend(b"abc");
+    p.wr().extend(b"def".iter().copied());
+    p.wr().extend((0..3).map(|i| b'0' + i).filter(|_| true));
+    p.extend(core::iter::empty::<u8>());
+    assert_eq!(b"abcdef012", p.rd().data());
+    p.extend([b'!'; 7]);
+    assert_eq!(16, p.rd().len());
+    p.extend(core::iter::empty::<u8>());
+    p.extend(b"");
+
+    // An overstated size hint does not make it spin
+    struct Liar(u8);
+    impl Iterator for Liar {
+        type Item = u8;
+        fn next(&mut self) -> Option<u8> {
+            self.0 = self.0.checked_sub(1)?;
+            Some(b'a' + self.0)
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (5, None)
+        }
+    }
+    p.reset();
+    p.extend(Liar(2));
+    assert_eq!(b"ba", p.rd().data());
+
+    // Fallible variant stops when space runs out
+    let mut it = b"0123456789ABCDEF".iter().copied();
+    assert_eq!(false, p.wr().try_extend(&mut it));
+    assert_eq!(b"ba0123456789ABCD", p.rd().data());
+    p.rd().consume(4);
+    assert_eq!(true, p.wr().try_extend(&mut it));
+    assert_eq!(b"23456789ABCDEF", p.rd().data());
+    p.wr().append(b"!!");
+    assert_eq!(false, p.wr().try_extend(&mut core::iter::once(b'?')));
+    p.set_writable(false);
+    p.rd().consume(1);
+    assert_eq!(false, p.wr().try_extend(&mut core::iter::once(b'?')));
+    p.set_writable(true);
+    assert_eq!(true, p.wr().try_extend(&mut core::iter::once(b'?')));
+    assert_eq!(b"3456789ABCDEF!!?", p.rd().data());
+
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    {
+        let p: PipeBuf<u16> = (1..=1000).collect();
+        assert_eq!(1000, p.readiness().len());
+        let mut p: PipeBuf = b"xyz".iter().copied().collect();
+        assert_eq!(b"xyz", p.rd().data());
+        assert_eq!(PBufState::Open, p.state());
+    }
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+#[should_panic]
+fn extend_overflow() {
+    let mut p = fixed_capacity_pipebuf!(4);
+    p.extend(b"abcde");
+}
+
+#[cfg(any(feature = "std", feature = "alloc", feature = "static"))]
+#[test]
+fn cursor() {
+    use pipebuf::PBufCursor;
+
+    // Count the complete lines, scanning each byte only once
+    fn lines(mut rd: PBufRd, cursor: &mut PBufCursor, scanned: &mut usize) -> usize {
+        let mut count = 0;
+        loop {
+            let unscanned = cursor.unscanned(&rd);
+            match unscanned.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    *scanned += i + 1;
+                    let len = cursor.pos() + i + 1;
+                    rd.consume(len);
+                    cursor.consumed(len);
+                    count += 1;
+                }
+                None => {
+                    *scanned += unscanned.len();
+                    cursor.advance(unscanned.len());
+                    return count;
+                }
+            }
+        }
+    }
+
+    let mut p = fixed_capacity_pipebuf!(16);
+    let mut c = PBufCursor::new();
+    let mut scanned = 0;
+    p.wr().append(b"abc\ndefg");
+    assert_eq!(1, lines(p.rd(), &mut c, &mut scanned));
+    assert_eq!(4, c.pos());
+    p.wr().append(b"hijklmn");
+    assert_eq!(0, lines(p.rd(), &mut c, &mut scanned));
+    assert_eq!(b"", c.unscanned(&p.rd()));
+    p.wr().append(b"op\nqr"); // compacts
+    assert_eq!(1, lines(p.rd(), &mut c, &mut scanned));
+    assert_eq!(b"qr", p.rd().data());
+    assert_eq!(2, c.pos());
+    assert_eq!(20, scanned);
+
+    // Position beyond the data gives an empty slice
+    p.rd().discard_tail(1);
+    assert_eq!(b"", c.unscanned(&p.rd()));
+    c.reset();
+    assert_eq!(b"q", c.unscanned(&p.rd()));
+    c.consumed(5);
+    assert_eq!(0, c.pos());
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+#[test]
+fn closed_aborted_with() {
+    let mut p = PipeBuf::closed_with(b"end");
+    assert_eq!(PBufState::Closing, p.state());
+    assert_eq!(b"end", p.rd().data());
+    p.rd().consume(3);
+    assert_eq!(true, p.rd().consume_eof());
+    assert_eq!(false, p.rd().is_aborted());
+    assert_eq!(true, p.is_done());
+
+    let mut p = PipeBuf::aborted_with(b"partial");
+    assert_eq!(PBufState::Aborting, p.state());
+    assert_eq!(b"partial", p.rd().data());
+    assert_eq!(true, p.rd().consume_eof());
+    assert_eq!(true, p.rd().is_aborted());
+
+    let p = PipeBuf::<u8>::closed_with(&[]);
+    assert_eq!(true, p.readiness().has_pending_eof());
+}
+
+#[cfg(all(
+    feature = "poison",
+    any(feature = "std", feature = "alloc", feature = "static")
+))]
+#[test]
+fn poison() {
+    let mut p = fixed_capacity_pipebuf!(8);
+    p.set_poison(Some(0xA5));
+    assert_eq!(&[0xA5; 4], p.wr().space(4));
+    assert_eq!(Some(&mut [0xA5; 8][..]), p.wr().try_space(8));
+
+    p.wr().append(b"abcdef");
+    p.rd().consume(4);
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    txn.wr().append(b"gh");
+    drop(txn);
+    p.wr().append(b"ijk"); // compacts
+    assert_eq!(b"efijk", p.rd().data());
+    p.rd().consume(5);
+    assert_eq!(&[0xA5; 8], p.wr().space(8));
+
+    p.set_poison(None);
+    p.wr().append(b"xy");
+    p.rd().consume(2);
+    assert_eq!(b"xy", &p.wr().space(8)[..2]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn bufread() {
+    use std::io::{BufRead, ErrorKind};
+    let mut p = PipeBuf::new();
+    assert_eq!(ErrorKind::WouldBlock, p.fill_buf().unwrap_err().kind());
+    p.wr().append(b"line 1\nline 2\npartial");
+
+    let mut line = String::new();
+    p.rd().read_line(&mut line).unwrap();
+    assert_eq!("line 1\n", line);
+    assert_eq!(b"line 2\n", &p.fill_buf().unwrap()[..7]);
+    BufRead::consume(&mut p, 7);
+    assert_eq!(b"partial", p.rd().fill_buf().unwrap());
+
+    p.wr().close();
+    let mut rest = Vec::new();
+    p.read_until(b'\n', &mut rest).unwrap();
+    assert_eq!(b"partial", &rest[..]);
+    assert_eq!(PBufState::Closed, p.state());
+    assert_eq!(b"", p.fill_buf().unwrap());
+    assert_eq!(b"", p.rd().fill_buf().unwrap());
+
+    let mut p = PipeBuf::new();
+    p.wr().abort();
+    assert_eq!(
+        ErrorKind::ConnectionAborted,
+        p.fill_buf().unwrap_err().kind()
+    );
+    assert_eq!(
+        ErrorKind::ConnectionAborted,
+        p.rd().fill_buf().unwrap_err().kind()
+    );
+}
+
+#[cfg(all(
+    feature = "waker",
+    any(feature = "std", feature = "alloc", feature = "static")
+))]
+#[test]
+fn waker() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    struct Count(AtomicUsize);
+    impl Wake for Count {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    let rd_count = Arc::new(Count(AtomicUsize::new(0)));
+    let wr_count = Arc::new(Count(AtomicUsize::new(0)));
+    let rd_waker = Waker::from(rd_count.clone());
+    let wr_waker = Waker::from(wr_count.clone());
+    let rd_woken = || rd_count.0.load(Ordering::SeqCst);
+    let wr_woken = || wr_count.0.load(Ordering::SeqCst);
+
+    let mut p = fixed_capacity_pipebuf!(8);
+    p.register_rd_waker(&rd_waker);
+    p.register_rd_waker(&rd_waker);
+    p.register_wr_waker(&wr_waker);
+    assert_eq!(0, rd_woken());
+    p.wr().append(b"abc");
+    assert_eq!(1, rd_woken());
+    p.wr().append(b"def");
+    assert_eq!(1, rd_woken()); // Woken only once
+    p.register_rd_waker(&rd_waker);
+    p.wr().push();
+    assert_eq!(2, rd_woken());
+    p.register_rd_waker(&rd_waker);
+    p.wr().close();
+    assert_eq!(3, rd_woken());
+
+    assert_eq!(0, wr_woken());
+    p.rd().consume(2);
+    assert_eq!(1, wr_woken());
+    p.register_wr_waker(&wr_waker);
+    p.rd().discard_tail(1);
+    assert_eq!(2, wr_woken());
+
+    // Re-enabling writes wakes the producer
+    p.register_wr_waker(&wr_waker);
+    p.set_writable(false);
+    assert_eq!(2, wr_woken());
+    p.set_writable(true);
+    assert_eq!(3, wr_woken());
+
+    // Empty commits and consumes don't wake, and reset drops wakers
+    p.reset();
+    p.register_rd_waker(&rd_waker);
+    p.register_wr_waker(&wr_waker);
+    p.wr().commit(0);
+    p.rd().consume(0);
+    assert_eq!(3, rd_woken());
+    assert_eq!(3, wr_woken());
+    p.reset();
+    p.wr().append(b"x");
+    p.rd().consume(1);
+    assert_eq!(3, rd_woken());
+    assert_eq!(3, wr_woken());
+
+    // Transactions only wake the consumer when the outermost one
+    // commits a change
+    p.register_rd_waker(&rd_waker);
+    let mut wr = p.wr();
+    let mut txn = wr.transaction();
+    txn.wr().append(b"ab");
+    txn.wr().push();
+    txn.rollback();
+    assert_eq!(3, rd_woken());
+    let mut txn = wr.transaction();
+    let mut wr2 = txn.wr();
+    let mut inner = wr2.transaction();
+    inner.wr().append(b"ab");
+    inner.commit();
+    assert_eq!(3, rd_woken());
+    txn.commit();
+    assert_eq!(4, rd_woken());
+    p.register_rd_waker(&rd_waker);
+    p.wr().transaction().commit();
+    assert_eq!(4, rd_woken());
+    p.wr().append(b"c");
+    assert_eq!(5, rd_woken());
+}